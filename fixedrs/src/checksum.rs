@@ -0,0 +1,232 @@
+// ==================== Stable checksums ====================
+
+//! Stable checksums of simulation state, for desync detection.
+//!
+//! `std::hash::Hash` may feed different bytes in different Rust versions, and the std hashers are
+//! randomly seeded, so neither suits comparing state across machines. `StableHasher` instead runs a
+//! fixed algorithm over a fixed encoding, set out below, which only changes along with
+//! `StableHasher::VERSION`. Peers should exchange the version with their checksums.
+//!
+//! Types feed themselves to the hasher through `StableHash`. Implement it for your own state by
+//! feeding the fields in a fixed order:
+//!
+//! ```
+//! use fixedrs::checksum::{StableHash, StableHasher};
+//! use fixedrs::{Q64, Q64Vec};
+//!
+//! struct Unit {
+//!   position: Q64Vec,
+//!   health: Q64,
+//!   name: String,
+//! }
+//!
+//! impl StableHash for Unit {
+//!   fn stable_hash(&self, hasher: &mut StableHasher) {
+//!     self.position.stable_hash(hasher);
+//!     self.health.stable_hash(hasher);
+//!     self.name.stable_hash(hasher);
+//!   }
+//! }
+//!
+//! let units = vec![Unit { position: Q64Vec::ZERO, health: Q64::ONE, name: "scout".into() }];
+//! let tick_checksum = fixedrs::checksum::checksum(&units);
+//! # assert_eq!(tick_checksum, fixedrs::checksum::checksum(&units));
+//! ```
+//!
+//! Version 1 is 64-bit FNV-1a over these encodings:
+//!
+//! - Integers are their little-endian bytes at their own width, except `usize` and `isize`, which
+//!   are widened to 64 bits so that every platform agrees.
+//! - `bool` is one byte, 0 or 1.
+//! - Fixed-point values are their raw bits, as an integer of the representation's width.
+//! - Vectors and quaternions are their coordinates in declaration order.
+//! - Slices, `Vec`s and strings are their length, as a `u64`, followed by their elements or bytes.
+//!   Arrays and tuples are just their elements.
+//! - `Option` is a 0 byte for `None`, or a 1 byte followed by the value.
+
+use crate::{Fixed, Q64Quat, Q64Vec, Q64Vec3};
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// A hasher whose output depends only on the bytes fed to it and `VERSION`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct StableHasher {
+  state: u64,
+}
+
+impl Default for StableHasher {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl StableHasher {
+  /// Identifies the algorithm and the encodings. Any change to either bumps it.
+  pub const VERSION: u32 = 1;
+
+  pub fn new() -> Self {
+    Self { state: FNV_OFFSET_BASIS }
+  }
+
+  pub fn write(&mut self, bytes: &[u8]) {
+    for &byte in bytes {
+      self.state = (self.state ^ byte as u64).wrapping_mul(FNV_PRIME);
+    }
+  }
+
+  /// Feeds `value` by its `StableHash` encoding.
+  pub fn update<T: StableHash + ?Sized>(&mut self, value: &T) {
+    value.stable_hash(self);
+  }
+
+  /// The checksum of everything fed so far. Feeding can continue afterwards.
+  pub fn finish(&self) -> u64 {
+    self.state
+  }
+}
+
+/// The checksum of a single value.
+pub fn checksum<T: StableHash + ?Sized>(value: &T) -> u64 {
+  let mut hasher = StableHasher::new();
+  value.stable_hash(&mut hasher);
+  hasher.finish()
+}
+
+/// A type with a stable encoding for `StableHasher`.
+pub trait StableHash {
+  fn stable_hash(&self, hasher: &mut StableHasher);
+}
+
+macro_rules! impl_stable_hash_int {
+  ($($t:ty)*) => {
+    $(
+      impl StableHash for $t {
+        fn stable_hash(&self, hasher: &mut StableHasher) {
+          hasher.write(&self.to_le_bytes());
+        }
+      }
+    )*
+  };
+}
+
+impl_stable_hash_int!(u8 u16 u32 u64 u128 i8 i16 i32 i64 i128);
+
+impl StableHash for usize {
+  fn stable_hash(&self, hasher: &mut StableHasher) {
+    (*self as u64).stable_hash(hasher);
+  }
+}
+
+impl StableHash for isize {
+  fn stable_hash(&self, hasher: &mut StableHasher) {
+    (*self as i64).stable_hash(hasher);
+  }
+}
+
+impl StableHash for bool {
+  fn stable_hash(&self, hasher: &mut StableHasher) {
+    (*self as u8).stable_hash(hasher);
+  }
+}
+
+impl<R: StableHash, const FRAC: u32> StableHash for Fixed<R, FRAC> {
+  fn stable_hash(&self, hasher: &mut StableHasher) {
+    self.0.stable_hash(hasher);
+  }
+}
+
+impl StableHash for Q64Vec {
+  fn stable_hash(&self, hasher: &mut StableHasher) {
+    self.x.stable_hash(hasher);
+    self.y.stable_hash(hasher);
+  }
+}
+
+impl StableHash for Q64Vec3 {
+  fn stable_hash(&self, hasher: &mut StableHasher) {
+    self.x.stable_hash(hasher);
+    self.y.stable_hash(hasher);
+    self.z.stable_hash(hasher);
+  }
+}
+
+impl StableHash for Q64Quat {
+  fn stable_hash(&self, hasher: &mut StableHasher) {
+    self.w.stable_hash(hasher);
+    self.x.stable_hash(hasher);
+    self.y.stable_hash(hasher);
+    self.z.stable_hash(hasher);
+  }
+}
+
+impl<T: StableHash> StableHash for [T] {
+  fn stable_hash(&self, hasher: &mut StableHasher) {
+    (self.len() as u64).stable_hash(hasher);
+    for item in self {
+      item.stable_hash(hasher);
+    }
+  }
+}
+
+impl<T: StableHash, const N: usize> StableHash for [T; N] {
+  fn stable_hash(&self, hasher: &mut StableHasher) {
+    for item in self {
+      item.stable_hash(hasher);
+    }
+  }
+}
+
+impl<T: StableHash> StableHash for Vec<T> {
+  fn stable_hash(&self, hasher: &mut StableHasher) {
+    self.as_slice().stable_hash(hasher);
+  }
+}
+
+impl StableHash for str {
+  fn stable_hash(&self, hasher: &mut StableHasher) {
+    (self.len() as u64).stable_hash(hasher);
+    hasher.write(self.as_bytes());
+  }
+}
+
+impl StableHash for String {
+  fn stable_hash(&self, hasher: &mut StableHasher) {
+    self.as_str().stable_hash(hasher);
+  }
+}
+
+impl<T: StableHash> StableHash for Option<T> {
+  fn stable_hash(&self, hasher: &mut StableHasher) {
+    match self {
+      None => 0u8.stable_hash(hasher),
+      Some(value) => {
+        1u8.stable_hash(hasher);
+        value.stable_hash(hasher);
+      }
+    }
+  }
+}
+
+impl<T: StableHash + ?Sized> StableHash for &T {
+  fn stable_hash(&self, hasher: &mut StableHasher) {
+    (**self).stable_hash(hasher);
+  }
+}
+
+macro_rules! impl_stable_hash_tuple {
+  ($($name:ident)+) => {
+    impl<$($name: StableHash),+> StableHash for ($($name,)+) {
+      #[allow(non_snake_case)]
+      fn stable_hash(&self, hasher: &mut StableHasher) {
+        let ($($name,)+) = self;
+        $($name.stable_hash(hasher);)+
+      }
+    }
+  };
+}
+
+impl_stable_hash_tuple!(A);
+impl_stable_hash_tuple!(A B);
+impl_stable_hash_tuple!(A B C);
+impl_stable_hash_tuple!(A B C D);
@@ -0,0 +1,311 @@
+// ==================== Exact decimal formatting and parsing ====================
+
+// Values are handled as a sign and a u128 magnitude in units of 2^-frac, so that one implementation
+// serves every layout, up to 127 fractional bits. Neither direction's cost grows faster than the
+// length of the string: formatting stops at the shortest or requested digits, and parsing keeps
+// only the first `MAX_DIGITS` significant digits plus a sticky bit for the rest.
+
+use crate::repr::widening_mul_u128;
+
+/// Why a string couldn't be parsed as a fixed-point number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ParseFixedError {
+  /// The string isn't a decimal literal like `-12.375` or `1e-3`.
+  Invalid,
+  /// The value is too large in magnitude for the type.
+  Overflow,
+}
+
+impl std::fmt::Display for ParseFixedError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.write_str(match self {
+      ParseFixedError::Invalid => "invalid fixed-point literal",
+      ParseFixedError::Overflow => "fixed-point literal out of range",
+    })
+  }
+}
+
+impl std::error::Error for ParseFixedError {}
+
+/// Formats `magnitude * 2^-frac` in decimal, without a sign. With a precision the result is rounded
+/// to that many places with ties to even; without one it is the shortest string that parses back to
+/// the same value.
+pub(crate) fn format(magnitude: u128, frac: u32, precision: Option<usize>) -> String {
+  let mask = (1u128 << frac) - 1;
+  let one = 1u128 << frac;
+  let mut integer = magnitude >> frac;
+  let mut fraction = magnitude & mask;
+  // Multiplies the fraction by ten, returning the digit that moves above the point. The product
+  // needs up to 131 bits.
+  let next_digit = |fraction: &mut u128| {
+    let (hi, lo) = widening_mul_u128(*fraction, 10);
+    *fraction = lo & mask;
+    match frac {
+      0 => 0,
+      _ => ((hi << (128 - frac)) | (lo >> frac)) as u8,
+    }
+  };
+  // The comparisons with twice the fraction are written against `one - fraction`, which can't
+  // overflow.
+  let mut digits: Vec<u8> = Vec::new();
+  let round_up = match precision {
+    Some(precision) => {
+      for _ in 0..precision {
+        digits.push(next_digit(&mut fraction));
+      }
+      let last_is_odd = match digits.last() {
+        Some(&digit) => digit % 2 == 1,
+        None => integer % 2 == 1,
+      };
+      let gap = one - fraction;
+      fraction > gap || (fraction == gap && last_is_odd)
+    }
+    None => {
+      // Any decimal strictly within half an ulp of the value parses back to it. `margin` is twice
+      // that distance, scaled along with `fraction`, so that everything stays an integer.
+      let mut margin = 1u128;
+      let mut round_up = false;
+      while fraction != 0 {
+        // Saturating is fine, since by then any margin covers the whole interval.
+        margin = margin.saturating_mul(10);
+        digits.push(next_digit(&mut fraction));
+        let gap = one - fraction;
+        // 2 * fraction < margin, and 2 * fraction + margin > 2 * one.
+        let can_round_down = margin.checked_sub(fraction).is_some_and(|rest| fraction < rest);
+        let can_round_up = margin.checked_sub(gap).is_some_and(|rest| rest > gap);
+        if can_round_down || can_round_up {
+          round_up = match (can_round_down, can_round_up) {
+            (true, true) => fraction >= gap,
+            (down, _) => !down,
+          };
+          break;
+        }
+      }
+      round_up
+    }
+  };
+  if round_up {
+    let mut carry = true;
+    for digit in digits.iter_mut().rev() {
+      *digit += 1;
+      carry = *digit == 10;
+      if !carry {
+        break;
+      }
+      *digit = 0;
+    }
+    if carry {
+      integer += 1;
+    }
+    if precision.is_none() {
+      while digits.last() == Some(&0) {
+        digits.pop();
+      }
+    }
+  }
+  let mut s = integer.to_string();
+  if !digits.is_empty() {
+    s.push('.');
+    s.extend(digits.iter().map(|&digit| (b'0' + digit) as char));
+  }
+  s
+}
+
+// Fixed-size arbitrary-precision unsigned integers, as little-endian 32-bit limbs, for exact
+// parsing. Parsing is const, so nothing can allocate; 768 bits holds every intermediate value.
+const LIMBS: usize = 24;
+type Big = [u32; LIMBS];
+
+/// Significant digits beyond this many only matter through whether any is nonzero. 170 digits of a
+/// value in range reach below 10^-128, and no digit that fine can decide a rounding tie.
+const MAX_DIGITS: i64 = 170;
+
+const fn big_mul_add(a: &mut Big, m: u32, add: u32) {
+  let mut carry = add as u64;
+  let mut i = 0;
+  while i < LIMBS {
+    let x = a[i] as u64 * m as u64 + carry;
+    a[i] = x as u32;
+    carry = x >> 32;
+    i += 1;
+  }
+}
+
+const fn big_bit_len(a: &Big) -> usize {
+  let mut i = LIMBS;
+  while i > 0 {
+    i -= 1;
+    if a[i] != 0 {
+      return i * 32 + 32 - a[i].leading_zeros() as usize;
+    }
+  }
+  0
+}
+
+const fn big_bit(a: &Big, i: usize) -> u32 {
+  (a[i / 32] >> (i % 32)) & 1
+}
+
+const fn big_cmp(a: &Big, b: &Big) -> std::cmp::Ordering {
+  let mut i = LIMBS;
+  while i > 0 {
+    i -= 1;
+    if a[i] != b[i] {
+      return match a[i] < b[i] {
+        true => std::cmp::Ordering::Less,
+        false => std::cmp::Ordering::Greater,
+      };
+    }
+  }
+  std::cmp::Ordering::Equal
+}
+
+/// `a -= b`, for `a >= b`.
+const fn big_sub(a: &mut Big, b: &Big) {
+  let mut borrow = 0i64;
+  let mut i = 0;
+  while i < LIMBS {
+    let x = a[i] as i64 - b[i] as i64 - borrow;
+    a[i] = x as u32;
+    borrow = (x < 0) as i64;
+    i += 1;
+  }
+}
+
+/// Parses a decimal literal into a sign and a magnitude in units of 2^-frac, rounded to nearest with
+/// ties to even. Fails with Overflow if the magnitude doesn't fit in a u128.
+pub(crate) const fn parse(s: &[u8], frac: u32) -> Result<(bool, u128), ParseFixedError> {
+  let mut i = 0;
+  let negative = !s.is_empty() && s[0] == b'-';
+  if !s.is_empty() && (s[0] == b'-' || s[0] == b'+') {
+    i = 1;
+  }
+
+  // The value is digits * 10^exponent, where digits holds the leading significant digits, and
+  // `sticky` says whether any dropped after them was nonzero.
+  let mut digits: Big = [0; LIMBS];
+  let mut significant: i64 = 0;
+  let mut exponent: i64 = 0;
+  let mut sticky = false;
+  let mut any_digits = false;
+  let mut seen_point = false;
+  while i < s.len() && s[i] != b'e' && s[i] != b'E' {
+    let b = s[i];
+    i += 1;
+    if b == b'.' && !seen_point {
+      seen_point = true;
+      continue;
+    }
+    if !b.is_ascii_digit() {
+      return Err(ParseFixedError::Invalid);
+    }
+    any_digits = true;
+    let digit = (b - b'0') as u32;
+    if significant == 0 && digit == 0 {
+      // A leading zero only matters after the point, where it shifts the rest down.
+      exponent -= seen_point as i64;
+    } else if significant < MAX_DIGITS {
+      big_mul_add(&mut digits, 10, digit);
+      significant += 1;
+      exponent -= seen_point as i64;
+    } else {
+      sticky |= digit != 0;
+      exponent += !seen_point as i64;
+    }
+  }
+  if !any_digits {
+    return Err(ParseFixedError::Invalid);
+  }
+  if i < s.len() {
+    i += 1;
+    let e_negative = i < s.len() && s[i] == b'-';
+    if i < s.len() && (s[i] == b'-' || s[i] == b'+') {
+      i += 1;
+    }
+    if i == s.len() {
+      return Err(ParseFixedError::Invalid);
+    }
+    let mut magnitude = 0i64;
+    while i < s.len() {
+      if !s[i].is_ascii_digit() {
+        return Err(ParseFixedError::Invalid);
+      }
+      // Saturate absurd exponents; the range checks below handle them.
+      magnitude = magnitude * 10 + (s[i] - b'0') as i64;
+      if magnitude > 1 << 40 {
+        magnitude = 1 << 40;
+      }
+      i += 1;
+    }
+    exponent += match e_negative {
+      true => -magnitude,
+      false => magnitude,
+    };
+  }
+
+  if significant == 0 {
+    return Ok((negative, 0));
+  }
+  // Position of the leading digit: the value is in [10^(magnitude_digits - 1), 10^magnitude_digits).
+  let magnitude_digits = significant + exponent;
+  if magnitude_digits > 39 {
+    return Err(ParseFixedError::Overflow);
+  }
+  // Below 10^-40 the value is less than half of the smallest ulp (2^-124).
+  if magnitude_digits < -40 {
+    return Ok((negative, 0));
+  }
+  if sticky {
+    // A final 1 stands in for the dropped digits: it rounds the same way, since they are nonzero
+    // and too fine to reach the next rounding boundary.
+    big_mul_add(&mut digits, 10, 1);
+    exponent -= 1;
+  }
+
+  let mut numerator = digits;
+  let mut denominator: Big = [0; LIMBS];
+  denominator[0] = 1;
+  let mut k = 0;
+  while k < exponent.unsigned_abs() {
+    match exponent < 0 {
+      true => big_mul_add(&mut denominator, 10, 0),
+      false => big_mul_add(&mut numerator, 10, 0),
+    }
+    k += 1;
+  }
+  let mut k = 0;
+  while k < frac {
+    big_mul_add(&mut numerator, 2, 0);
+    k += 1;
+  }
+
+  // Shift-and-subtract long division.
+  let mut quotient = 0u128;
+  let mut remainder: Big = [0; LIMBS];
+  let mut i = big_bit_len(&numerator);
+  while i > 0 {
+    i -= 1;
+    big_mul_add(&mut remainder, 2, big_bit(&numerator, i));
+    let bit = !matches!(big_cmp(&remainder, &denominator), std::cmp::Ordering::Less);
+    if bit {
+      big_sub(&mut remainder, &denominator);
+    }
+    if quotient >> 127 != 0 {
+      return Err(ParseFixedError::Overflow);
+    }
+    quotient = (quotient << 1) | bit as u128;
+  }
+  big_mul_add(&mut remainder, 2, 0);
+  let round_up = match big_cmp(&remainder, &denominator) {
+    std::cmp::Ordering::Greater => true,
+    std::cmp::Ordering::Equal => quotient % 2 == 1,
+    std::cmp::Ordering::Less => false,
+  };
+  if round_up {
+    quotient = match quotient.checked_add(1) {
+      Some(quotient) => quotient,
+      None => return Err(ParseFixedError::Overflow),
+    };
+  }
+  Ok((negative, quotient))
+}
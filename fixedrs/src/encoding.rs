@@ -0,0 +1,139 @@
+// ==================== Binary encodings ====================
+
+//! Compact, deterministic binary encodings, for things like network snapshots.
+//!
+//! Values are written as zigzag varints: the raw bits are mapped so that small magnitudes of either
+//! sign become small unsigned integers, which are then written seven bits per byte (LEB128). The
+//! delta helpers write a count followed by each value's wrapping difference from the previous one,
+//! which is small for sorted or slowly changing data, and exact whatever the values are.
+//!
+//! Every value has exactly one encoding, and decoding rejects the others. For fixed-size encodings
+//! see `to_le_bytes`, and for serde formats like bincode see `fixedrs::serde::raw_int`.
+
+use crate::{Q64Vec, Q64};
+
+/// Why a byte string couldn't be decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DecodeError {
+  /// The input ended in the middle of a value.
+  UnexpectedEnd,
+  /// A varint was too long for its type, or had redundant trailing bytes.
+  Invalid,
+}
+
+impl std::fmt::Display for DecodeError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.write_str(match self {
+      DecodeError::UnexpectedEnd => "unexpected end of input",
+      DecodeError::Invalid => "invalid varint",
+    })
+  }
+}
+
+impl std::error::Error for DecodeError {}
+
+fn write_uvarint(out: &mut Vec<u8>, mut x: u64) {
+  while x >= 0x80 {
+    out.push(x as u8 | 0x80);
+    x >>= 7;
+  }
+  out.push(x as u8);
+}
+
+fn read_uvarint(input: &mut &[u8]) -> Result<u64, DecodeError> {
+  let mut x = 0u64;
+  for i in 0..10 {
+    let (&byte, rest) = input.split_first().ok_or(DecodeError::UnexpectedEnd)?;
+    *input = rest;
+    // The tenth byte holds only the top bit, and a final zero byte would be redundant.
+    if (i == 9 && byte > 1) || (i > 0 && byte == 0) {
+      return Err(DecodeError::Invalid);
+    }
+    x |= ((byte & 0x7f) as u64) << (7 * i);
+    if byte < 0x80 {
+      return Ok(x);
+    }
+  }
+  Err(DecodeError::Invalid)
+}
+
+/// Appends `x` as a zigzag varint, which takes between 1 and 10 bytes.
+pub fn write_varint(out: &mut Vec<u8>, x: i64) {
+  write_uvarint(out, ((x << 1) ^ (x >> 63)) as u64);
+}
+
+/// Reads a zigzag varint from the front of `input`, advancing it past the bytes consumed.
+pub fn read_varint(input: &mut &[u8]) -> Result<i64, DecodeError> {
+  let x = read_uvarint(input)?;
+  Ok((x >> 1) as i64 ^ -((x & 1) as i64))
+}
+
+pub fn write_q64(out: &mut Vec<u8>, x: Q64) {
+  write_varint(out, x.to_bits());
+}
+
+pub fn read_q64(input: &mut &[u8]) -> Result<Q64, DecodeError> {
+  read_varint(input).map(Q64::from_bits)
+}
+
+pub fn write_q64_vec(out: &mut Vec<u8>, v: Q64Vec) {
+  write_q64(out, v.x);
+  write_q64(out, v.y);
+}
+
+pub fn read_q64_vec(input: &mut &[u8]) -> Result<Q64Vec, DecodeError> {
+  Ok(Q64Vec::new(read_q64(input)?, read_q64(input)?))
+}
+
+/// Reads a count of values that each take at least `min_bytes`, checking it against the input so
+/// that a corrupt count can't cause a huge allocation.
+fn read_count(input: &mut &[u8], min_bytes: usize) -> Result<usize, DecodeError> {
+  let count = read_uvarint(input)?;
+  match count <= (input.len() / min_bytes) as u64 {
+    true => Ok(count as usize),
+    false => Err(DecodeError::UnexpectedEnd),
+  }
+}
+
+/// Appends the number of values, then each value's difference from the previous one (the first is
+/// relative to zero).
+pub fn write_q64_deltas(out: &mut Vec<u8>, values: &[Q64]) {
+  write_uvarint(out, values.len() as u64);
+  let mut previous = Q64::ZERO;
+  for &x in values {
+    write_q64(out, x.wrapping_sub(previous));
+    previous = x;
+  }
+}
+
+pub fn read_q64_deltas(input: &mut &[u8]) -> Result<Vec<Q64>, DecodeError> {
+  let count = read_count(input, 1)?;
+  let mut values = Vec::with_capacity(count);
+  let mut previous = Q64::ZERO;
+  for _ in 0..count {
+    previous = previous.wrapping_add(read_q64(input)?);
+    values.push(previous);
+  }
+  Ok(values)
+}
+
+/// Like `write_q64_deltas`, with the differences taken per coordinate.
+pub fn write_q64_vec_deltas(out: &mut Vec<u8>, values: &[Q64Vec]) {
+  write_uvarint(out, values.len() as u64);
+  let mut previous = Q64Vec::ZERO;
+  for &v in values {
+    write_q64_vec(out, v.wrapping_sub(previous));
+    previous = v;
+  }
+}
+
+pub fn read_q64_vec_deltas(input: &mut &[u8]) -> Result<Vec<Q64Vec>, DecodeError> {
+  let count = read_count(input, 2)?;
+  let mut values = Vec::with_capacity(count);
+  let mut previous = Q64Vec::ZERO;
+  for _ in 0..count {
+    previous = previous.wrapping_add(read_q64_vec(input)?);
+    values.push(previous);
+  }
+  Ok(values)
+}
@@ -0,0 +1,160 @@
+// ==================== Exponential and logarithm kernels ====================
+
+// Like the trig kernels these work in Q.62 independent of the caller's layout. Both reduce their
+// argument with a 16-entry table and finish with a short series.
+
+use crate::repr::{div_rem_u256, widening_mul_u128, FixedRepr};
+use crate::trig::{even_poly_q62, mul_q62, Q62_SHIFT};
+
+const LN_2_Q62: i64 = 3196577161300663915;
+const LOG2_E_Q62: i64 = 6653256548922161246;
+
+// ln(1 + k/16) for k in 0..=16, in Q2.62.
+const LN_TABLE: [i64; 17] = [
+  0, 279581720803300556, 543178378744478074, 792519427131828708, 1029067995697975510,
+  1254072913634921988, 1468608619315380861, 1673606191257749149, 1869877770022570995,
+  2058135991395951020, 2239009605877689523, 2413056148767049069, 2580772304913014908,
+  2742602453854813247, 2898945765720546505, 3050162133180044765, 3196577161300663915,
+];
+
+// 2^(k/16) for k in 0..16, in Q2.62.
+const EXP2_TABLE: [i64; 16] = [
+  4611686018427387904, 4815862801830788490, 5029079263719320435, 5251735624851448219,
+  5484249825272419512, 5727058308814112983, 5980616842327661685, 6245401371186603363,
+  6521908912666391106, 6810658488877194079, 7112192101001162095, 7427075746662858866,
+  7755900482342532474, 8099283532826439817, 8457869449776733335, 8832331321595618838,
+];
+
+// Coefficients 1 / (2n + 1) of atanh(s) / s, in Q2.62. After reduction |s| <= 1/64.
+const ATANH_COEFFICIENTS: [i64; 6] = [
+  4611686018427387904, 1537228672809129301, 922337203685477581, 658812288346769701,
+  512409557603043100, 419244183493398900,
+];
+
+// Coefficients 1 / n! of exp, in Q2.62. After reduction the argument is below ln(2) / 16.
+const EXP_COEFFICIENTS: [i64; 10] = [
+  4611686018427387904, 4611686018427387904, 2305843009213693952, 768614336404564651,
+  192153584101141163, 38430716820228233, 6405119470038039, 915017067148291, 114377133393536,
+  12708570377060,
+];
+
+/// ln(m) for m in [1, 2) in Q2.62, using ln(m) = ln(c) + 2 atanh((m - c) / (m + c)) with c the
+/// nearest multiple of 1/16.
+fn ln_mantissa_q62(m: i64) -> i64 {
+  let k = ((m - (1 << Q62_SHIFT) + (1 << 57)) >> 58) as usize;
+  let c = (1i128 << Q62_SHIFT) + ((k as i128) << 58);
+  let s = (((m as i128 - c) << Q62_SHIFT) / (m as i128 + c)) as i64;
+  LN_TABLE[k] + 2 * mul_q62(s, even_poly_q62(&ATANH_COEFFICIENTS, mul_q62(s, s)))
+}
+
+/// Splits a positive value `raw * 2^-frac` into `2^e * m` with m in [1, 2), returning e and ln(m).
+fn split_log(raw: u128, frac: u32) -> (i128, i64) {
+  let top_bit = 127 - raw.leading_zeros();
+  let m = match top_bit >= Q62_SHIFT {
+    true => raw >> (top_bit - Q62_SHIFT),
+    false => raw << (Q62_SHIFT - top_bit),
+  } as i64;
+  (top_bit as i128 - frac as i128, ln_mantissa_q62(m))
+}
+
+/// ln of the positive value `raw * 2^-frac`, in Q.62.
+pub(crate) fn ln_q62(raw: u128, frac: u32) -> i128 {
+  let (e, ln_m) = split_log(raw, frac);
+  e * LN_2_Q62 as i128 + ln_m as i128
+}
+
+/// log2 of the positive value `raw * 2^-frac`, in Q.62.
+pub(crate) fn log2_q62(raw: u128, frac: u32) -> i128 {
+  let (e, ln_m) = split_log(raw, frac);
+  (e << Q62_SHIFT) + mul_q62(ln_m, LOG2_E_Q62) as i128
+}
+
+/// `a * b` for Q.62 values, saturating to +-2^126, which is far outside the range where exp2 of
+/// the result is representable.
+pub(crate) fn mul_q62_saturating(a: i128, b: i128) -> i128 {
+  match a.overflowing_mul_shr(b, Q62_SHIFT) {
+    (x, false) => x.clamp(-1 << 126, 1 << 126),
+    (_, true) if (a < 0) != (b < 0) => -1 << 126,
+    (_, true) => 1 << 126,
+  }
+}
+
+/// `t * log2(e)` for a Q.62 value, so that `exp(t) = exp2(t * log2(e))`.
+pub(crate) fn exp_to_exp2_q62(t: i128) -> i128 {
+  mul_q62_saturating(t, LOG2_E_Q62 as i128)
+}
+
+/// 2^t for t in Q.62, as a raw value with `frac` fractional bits, rounded to nearest. Returns None
+/// if the result doesn't fit in an i128.
+pub(crate) fn exp2_q62(t: i128, frac: u32) -> Option<i128> {
+  let k = t >> Q62_SHIFT;
+  let f = t & ((1 << Q62_SHIFT) - 1);
+  let j = (f >> 58) as usize;
+  let h = mul_q62((f - ((j as i128) << 58)) as i64, LN_2_Q62);
+  let exp_h = EXP_COEFFICIENTS.iter().rev().fold(0, |acc, &c| c + mul_q62(h, acc));
+  // m is in [1, 2), and the result is m * 2^(k + frac - 62) in units of 2^-frac.
+  let m = mul_q62(EXP2_TABLE[j], exp_h) as i128;
+  let shift = k + frac as i128 - Q62_SHIFT as i128;
+  match shift >= 0 {
+    true if shift > 64 => None,
+    true => Some(m << shift),
+    false if shift < -126 => Some(0),
+    false => Some((m + (1 << (-shift - 1))) >> -shift),
+  }
+}
+
+/// A power carried as `m * 2^e`, with `m` in [2^126, 2^127).
+#[derive(Clone, Copy)]
+struct WidePower {
+  m: u128,
+  e: i64,
+}
+
+impl WidePower {
+  fn new(raw: u128, frac: u32) -> Self {
+    match raw.leading_zeros() {
+      0 => Self { m: raw >> 1, e: 1 - frac as i64 },
+      zeros => Self { m: raw << (zeros - 1), e: 1 - zeros as i64 - frac as i64 },
+    }
+  }
+
+  /// The product, truncated to 127 significant bits.
+  fn mul(self, rhs: Self) -> Self {
+    let (hi, lo) = widening_mul_u128(self.m, rhs.m);
+    // The product is in [2^252, 2^254).
+    match hi >> 125 {
+      0 => Self { m: (hi << 2) | (lo >> 126), e: self.e + rhs.e + 126 },
+      _ => Self { m: (hi << 1) | (lo >> 127), e: self.e + rhs.e + 127 },
+    }
+  }
+}
+
+/// The reciprocal of `(raw * 2^-frac)^n`, as a raw value with `frac` fractional bits, rounded toward
+/// zero. The power is carried to 127 significant bits, so the result is exact whenever the power
+/// is, and within an ulp otherwise. Returns None if the result exceeds `limit`. Requires nonzero
+/// `raw` and `n`.
+pub(crate) fn recip_powi(raw: u128, frac: u32, mut n: u32, limit: u128) -> Option<u128> {
+  let mut base = WidePower::new(raw, frac);
+  let mut power = None::<WidePower>;
+  loop {
+    if n & 1 == 1 {
+      power = Some(power.map_or(base, |power| power.mul(base)));
+    }
+    n >>= 1;
+    if n == 0 {
+      break;
+    }
+    base = base.mul(base);
+  }
+  let power = power.unwrap();
+  // The reciprocal is 2^(frac - e) / m = (2^253 / m) >> (e + 253 - frac), and 2^253 / m is in
+  // (2^126, 2^127], so any left shift overflows.
+  let (q, _, _) = div_rem_u256((1 << 125, 0), power.m);
+  let shift = power.e + 253 - frac as i64;
+  let magnitude = match shift {
+    ..=-1 => return None,
+    0..=127 => q >> shift,
+    _ => 0,
+  };
+  (magnitude <= limit).then_some(magnitude)
+}
@@ -0,0 +1,470 @@
+// ==================== Geometry ====================
+
+//! Shapes built on `Q64Vec`, with overlap tests, closest-point queries and ray casts.
+//!
+//! All shapes are closed, so shapes that merely touch overlap, and every query is deterministic,
+//! including its tie-breaking. The circle tests compare exact distances in 128 bits, so they hold
+//! for any coordinates. Elsewhere the intermediate cross and dot products can overflow for
+//! coordinates far beyond 2^15 apart, and distances returned as a `Q64` for points 2^31 or more
+//! apart.
+
+use crate::{Q64Affine2, Q64Vec, Q64};
+use num_integer::Roots;
+
+/// The distance between two points in raw `Q64` units, rounded down like `Q64Vec::distance`, which
+/// it equals whenever that doesn't overflow.
+pub(crate) fn distance_bits(a: Q64Vec, b: Q64Vec) -> u128 {
+  let dx = (b.x.0 as i128 - a.x.0 as i128).unsigned_abs();
+  let dy = (b.y.0 as i128 - a.y.0 as i128).unsigned_abs();
+  match dx.checked_mul(dx).zip(dy.checked_mul(dy)).and_then(|(x, y)| x.checked_add(y)) {
+    Some(sum) => sum.sqrt(),
+    // Only for points about 2^31 apart, where halving loses just the low bit.
+    None => 2 * ((dx >> 1) * (dx >> 1) + (dy >> 1) * (dy >> 1)).sqrt(),
+  }
+}
+
+/// The first point where a ray meets a shape.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RayHit {
+  /// The hit point is `origin + direction * t`, with `t` nonnegative.
+  pub t: Q64,
+  pub point: Q64Vec,
+  /// The unit outward normal of the surface at the hit point, or zero if the ray starts inside.
+  pub normal: Q64Vec,
+}
+
+/// The `RayHit` for a ray that starts inside the shape.
+fn inside_hit(ray: &Ray) -> RayHit {
+  RayHit {
+    t: Q64::ZERO,
+    point: ray.origin,
+    normal: Q64Vec::ZERO,
+  }
+}
+
+/// Whether `num / den` is in [0, 1], decided exactly rather than after rounding the division.
+fn ratio_in_unit_interval(num: Q64, den: Q64) -> bool {
+  match den < Q64::ZERO {
+    true => den <= num && num <= Q64::ZERO,
+    false => Q64::ZERO <= num && num <= den,
+  }
+}
+
+// ==================== Aabb ====================
+
+/// An axis-aligned bounding box, with `min <= max` in both coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Aabb {
+  pub min: Q64Vec,
+  pub max: Q64Vec,
+}
+
+impl Aabb {
+  /// The box with the two points as opposite corners, in either order.
+  pub fn new(a: Q64Vec, b: Q64Vec) -> Self {
+    Self {
+      min: Q64Vec::new(a.x.min(b.x), a.y.min(b.y)),
+      max: Q64Vec::new(a.x.max(b.x), a.y.max(b.y)),
+    }
+  }
+
+  pub fn from_center_half_extents(center: Q64Vec, half_extents: Q64Vec) -> Self {
+    Self::new(center - half_extents, center + half_extents)
+  }
+
+  /// The smallest box containing all the points, or `None` if there are none.
+  pub fn from_points(points: &[Q64Vec]) -> Option<Self> {
+    let (&first, rest) = points.split_first()?;
+    Some(rest.iter().fold(Self::new(first, first), |aabb, &p| aabb.union(&Self::new(p, p))))
+  }
+
+  pub fn center(&self) -> Q64Vec {
+    self.min.lerp(self.max, Q64::HALF)
+  }
+
+  pub fn half_extents(&self) -> Q64Vec {
+    (self.max - self.min) * Q64::HALF
+  }
+
+  pub fn contains_point(&self, p: Q64Vec) -> bool {
+    self.min.x <= p.x && p.x <= self.max.x && self.min.y <= p.y && p.y <= self.max.y
+  }
+
+  pub fn overlaps(&self, other: &Aabb) -> bool {
+    self.min.x <= other.max.x && other.min.x <= self.max.x && self.min.y <= other.max.y && other.min.y <= self.max.y
+  }
+
+  pub fn overlaps_circle(&self, circle: &Circle) -> bool {
+    circle.contains_point(self.closest_point(circle.center))
+  }
+
+  /// The nearest point of the box, which is `p` itself if it is inside.
+  pub fn closest_point(&self, p: Q64Vec) -> Q64Vec {
+    Q64Vec::new(p.x.clamp(self.min.x, self.max.x), p.y.clamp(self.min.y, self.max.y))
+  }
+
+  pub fn union(&self, other: &Aabb) -> Self {
+    Self {
+      min: Q64Vec::new(self.min.x.min(other.min.x), self.min.y.min(other.min.y)),
+      max: Q64Vec::new(self.max.x.max(other.max.x), self.max.y.max(other.max.y)),
+    }
+  }
+
+  /// Grown by `margin` on every side.
+  pub fn expanded(&self, margin: Q64) -> Self {
+    let margin = Q64Vec::new(margin, margin);
+    Self::new(self.min - margin, self.max + margin)
+  }
+
+  pub fn to_polygon(&self) -> Polygon {
+    Polygon {
+      vertices: vec![self.min, Q64Vec::new(self.max.x, self.min.y), self.max, Q64Vec::new(self.min.x, self.max.y)],
+    }
+  }
+}
+
+// ==================== Circle ====================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Circle {
+  pub center: Q64Vec,
+  pub radius: Q64,
+}
+
+impl Circle {
+  pub fn new(center: Q64Vec, radius: Q64) -> Self {
+    Self { center, radius }
+  }
+
+  pub fn contains_point(&self, p: Q64Vec) -> bool {
+    distance_bits(self.center, p) as i128 <= self.radius.0 as i128
+  }
+
+  pub fn overlaps(&self, other: &Circle) -> bool {
+    distance_bits(self.center, other.center) as i128 <= self.radius.0 as i128 + other.radius.0 as i128
+  }
+
+  /// The nearest point of the disc, which is `p` itself if it is inside.
+  pub fn closest_point(&self, p: Q64Vec) -> Q64Vec {
+    match self.contains_point(p) {
+      true => p,
+      false => self.center + (p - self.center).normalized() * self.radius,
+    }
+  }
+
+  pub fn aabb(&self) -> Aabb {
+    Aabb::from_center_half_extents(self.center, Q64Vec::new(self.radius, self.radius))
+  }
+}
+
+// ==================== Segment ====================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Segment {
+  pub a: Q64Vec,
+  pub b: Q64Vec,
+}
+
+impl Segment {
+  pub fn new(a: Q64Vec, b: Q64Vec) -> Self {
+    Self { a, b }
+  }
+
+  pub fn length(&self) -> Q64 {
+    self.a.distance(self.b)
+  }
+
+  pub fn closest_point(&self, p: Q64Vec) -> Q64Vec {
+    let r = self.b - self.a;
+    let length_squared = r.norm_squared();
+    if length_squared == Q64::ZERO {
+      return self.a;
+    }
+    let t = ((p - self.a).dot(r) / length_squared).clamp(Q64::ZERO, Q64::ONE);
+    self.a + r * t
+  }
+
+  pub fn distance_to_point(&self, p: Q64Vec) -> Q64 {
+    self.closest_point(p).distance(p)
+  }
+
+  /// A point common to both segments, or `None` if they don't meet. When collinear segments
+  /// overlap along a stretch, this is the point of the stretch nearest to `self.a`.
+  pub fn intersection(&self, other: &Segment) -> Option<Q64Vec> {
+    let r = self.b - self.a;
+    let s = other.b - other.a;
+    // Degenerate segments are single points.
+    if r == Q64Vec::ZERO {
+      return (other.closest_point(self.a) == self.a).then_some(self.a);
+    }
+    if s == Q64Vec::ZERO {
+      return (self.closest_point(other.a) == other.a).then_some(other.a);
+    }
+    let offset = other.a - self.a;
+    let denominator = r.cross(s);
+    if denominator == Q64::ZERO {
+      if offset.cross(r) != Q64::ZERO {
+        return None;
+      }
+      // Collinear: intersect the parameter ranges of the two segments along `self`.
+      let length_squared = r.norm_squared();
+      let t0 = offset.dot(r) / length_squared;
+      let t1 = (other.b - self.a).dot(r) / length_squared;
+      let start = t0.min(t1).max(Q64::ZERO);
+      let end = t0.max(t1).min(Q64::ONE);
+      return (start <= end).then(|| self.a + r * start);
+    }
+    let t_numerator = offset.cross(s);
+    let u_numerator = offset.cross(r);
+    match ratio_in_unit_interval(t_numerator, denominator) && ratio_in_unit_interval(u_numerator, denominator) {
+      true => Some(self.a + r * t_numerator / denominator),
+      false => None,
+    }
+  }
+
+  pub fn aabb(&self) -> Aabb {
+    Aabb::new(self.a, self.b)
+  }
+}
+
+// ==================== Ray ====================
+
+/// A half-line. `direction` needn't be normalized, and hit times are in units of it, so a ray from
+/// one point to another hits shapes between them at times in [0, 1].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Ray {
+  pub origin: Q64Vec,
+  pub direction: Q64Vec,
+}
+
+impl Ray {
+  pub fn new(origin: Q64Vec, direction: Q64Vec) -> Self {
+    Self { origin, direction }
+  }
+
+  pub fn point_at(&self, t: Q64) -> Q64Vec {
+    self.origin + self.direction * t
+  }
+
+  fn hit(&self, t: Q64, normal: Q64Vec) -> RayHit {
+    RayHit {
+      t,
+      point: self.point_at(t),
+      normal,
+    }
+  }
+
+  pub fn cast_circle(&self, circle: &Circle) -> Option<RayHit> {
+    if circle.contains_point(self.origin) {
+      return Some(inside_hit(self));
+    }
+    let length = self.direction.norm();
+    if length == Q64::ZERO {
+      return None;
+    }
+    // Work in distances along the unit direction, so that nothing is squared but the radius.
+    let unit = self.direction.normalized();
+    let to_center = circle.center - self.origin;
+    let along = to_center.dot(unit);
+    let off_axis = (to_center - unit * along).norm();
+    if along < Q64::ZERO || off_axis > circle.radius {
+      return None;
+    }
+    let half_chord = ((circle.radius - off_axis) * (circle.radius + off_axis)).sqrt();
+    let t = (along - half_chord).max(Q64::ZERO) / length;
+    let point = self.point_at(t);
+    Some(RayHit {
+      t,
+      point,
+      normal: (point - circle.center).normalized(),
+    })
+  }
+
+  /// Uses the slab method. At a corner, the normal is that of the vertical face.
+  pub fn cast_aabb(&self, aabb: &Aabb) -> Option<RayHit> {
+    if aabb.contains_point(self.origin) {
+      return Some(inside_hit(self));
+    }
+    let mut enter = Q64::MIN;
+    let mut exit = Q64::MAX;
+    let mut normal = Q64Vec::ZERO;
+    let axes = [
+      (self.origin.x, self.direction.x, aabb.min.x, aabb.max.x, Q64Vec::from_i32(1, 0)),
+      (self.origin.y, self.direction.y, aabb.min.y, aabb.max.y, Q64Vec::from_i32(0, 1)),
+    ];
+    for (origin, direction, min, max, axis) in axes {
+      if direction == Q64::ZERO {
+        if origin < min || origin > max {
+          return None;
+        }
+        continue;
+      }
+      let (near, far, face_normal) = match direction > Q64::ZERO {
+        true => (min, max, -axis),
+        false => (max, min, axis),
+      };
+      let t_near = (near - origin).saturating_div(direction);
+      if t_near > enter {
+        enter = t_near;
+        normal = face_normal;
+      }
+      exit = exit.min((far - origin).saturating_div(direction));
+    }
+    (enter <= exit && exit >= Q64::ZERO).then(|| self.hit(enter.max(Q64::ZERO), normal))
+  }
+
+  pub fn cast_segment(&self, segment: &Segment) -> Option<RayHit> {
+    let s = segment.b - segment.a;
+    let denominator = self.direction.cross(s);
+    // Parallel rays, including ones running along the segment, don't hit it.
+    if denominator == Q64::ZERO {
+      return None;
+    }
+    let offset = segment.a - self.origin;
+    let t_numerator = offset.cross(s);
+    let u_numerator = offset.cross(self.direction);
+    let t_nonnegative = (t_numerator < Q64::ZERO) == (denominator < Q64::ZERO) || t_numerator == Q64::ZERO;
+    if !t_nonnegative || !ratio_in_unit_interval(u_numerator, denominator) {
+      return None;
+    }
+    let mut normal = s.perp().normalized();
+    if normal.dot(self.direction) > Q64::ZERO {
+      normal = -normal;
+    }
+    Some(self.hit(t_numerator / denominator, normal))
+  }
+
+  /// Clips the ray against each edge in turn. At a vertex, the normal is that of the earlier edge.
+  pub fn cast_polygon(&self, polygon: &Polygon) -> Option<RayHit> {
+    if polygon.contains_point(self.origin) {
+      return Some(inside_hit(self));
+    }
+    let mut enter = Q64::MIN;
+    let mut exit = Q64::MAX;
+    let mut normal = Q64Vec::ZERO;
+    for (a, b) in polygon.edges() {
+      let outward = -(b - a).perp();
+      let distance = outward.dot(a - self.origin);
+      let approach = outward.dot(self.direction);
+      if approach == Q64::ZERO {
+        if distance < Q64::ZERO {
+          return None;
+        }
+        continue;
+      }
+      let t = distance.saturating_div(approach);
+      if approach > Q64::ZERO {
+        exit = exit.min(t);
+      } else if t > enter {
+        enter = t;
+        normal = outward;
+      }
+    }
+    (enter <= exit && exit >= Q64::ZERO && normal != Q64Vec::ZERO)
+      .then(|| self.hit(enter.max(Q64::ZERO), normal.normalized()))
+  }
+}
+
+// ==================== Polygon ====================
+
+/// A convex polygon, with its vertices in counterclockwise order.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Polygon {
+  vertices: Vec<Q64Vec>,
+}
+
+impl Polygon {
+  /// Accepts the vertices of a convex polygon in either winding order, storing them counterclockwise.
+  /// Returns `None` for fewer than three vertices, for a polygon that isn't convex, and for one with
+  /// zero area. Collinear vertices are allowed.
+  pub fn new(vertices: Vec<Q64Vec>) -> Option<Self> {
+    if vertices.len() < 3 {
+      return None;
+    }
+    let mut polygon = Self { vertices };
+    let twice_area = polygon.edges().fold(Q64::ZERO, |sum, (a, b)| sum + a.cross(b));
+    match twice_area {
+      Q64::ZERO => return None,
+      area if area < Q64::ZERO => polygon.vertices.reverse(),
+      _ => {}
+    }
+    // Convex exactly when every vertex is on or to the left of every edge.
+    let convex = polygon.edges().all(|(a, b)| polygon.vertices.iter().all(|&v| (b - a).cross(v - a) >= Q64::ZERO));
+    convex.then_some(polygon)
+  }
+
+  pub fn vertices(&self) -> &[Q64Vec] {
+    &self.vertices
+  }
+
+  /// The edges as pairs of consecutive vertices, counterclockwise.
+  pub fn edges(&self) -> impl Iterator<Item = (Q64Vec, Q64Vec)> + '_ {
+    let n = self.vertices.len();
+    (0..n).map(move |i| (self.vertices[i], self.vertices[(i + 1) % n]))
+  }
+
+  /// The polygon with every vertex transformed, or `None` if the transform collapses it.
+  pub fn transformed(&self, transform: &Q64Affine2) -> Option<Self> {
+    Self::new(self.vertices.iter().map(|&v| transform.transform_point(v)).collect())
+  }
+
+  /// Like `transformed`, for a rotation and translation, which keep the polygon convex and
+  /// counterclockwise up to rounding. Skips the checks, which that rounding could upset.
+  pub(crate) fn rigidly_transformed(&self, transform: &Q64Affine2) -> Self {
+    Self {
+      vertices: self.vertices.iter().map(|&v| transform.transform_point(v)).collect(),
+    }
+  }
+
+  pub fn aabb(&self) -> Aabb {
+    Aabb::from_points(&self.vertices).unwrap()
+  }
+
+  pub fn contains_point(&self, p: Q64Vec) -> bool {
+    self.edges().all(|(a, b)| (b - a).cross(p - a) >= Q64::ZERO)
+  }
+
+  /// The nearest point of the polygon, which is `p` itself if it is inside. On a tie the earliest
+  /// edge wins.
+  pub fn closest_point(&self, p: Q64Vec) -> Q64Vec {
+    if self.contains_point(p) {
+      return p;
+    }
+    let mut best = self.vertices[0];
+    let mut best_distance = None;
+    for (a, b) in self.edges() {
+      let candidate = Segment::new(a, b).closest_point(p);
+      let distance = candidate.distance(p);
+      if best_distance.is_none_or(|best_distance| distance < best_distance) {
+        best = candidate;
+        best_distance = Some(distance);
+      }
+    }
+    best
+  }
+
+  /// The range of `vertex.dot(axis)` over the vertices.
+  pub(crate) fn project(&self, axis: Q64Vec) -> (Q64, Q64) {
+    let mut dots = self.vertices.iter().map(|v| v.dot(axis));
+    let first = dots.next().unwrap();
+    dots.fold((first, first), |(min, max), d| (min.min(d), max.max(d)))
+  }
+
+  /// Uses the separating axis theorem.
+  pub fn overlaps(&self, other: &Polygon) -> bool {
+    let separated_along = |axis: Q64Vec| {
+      let (min_a, max_a) = self.project(axis);
+      let (min_b, max_b) = other.project(axis);
+      max_a < min_b || max_b < min_a
+    };
+    !self.edges().chain(other.edges()).any(|(a, b)| separated_along((b - a).perp()))
+  }
+
+  pub fn overlaps_circle(&self, circle: &Circle) -> bool {
+    circle.contains_point(self.closest_point(circle.center))
+  }
+
+  pub fn overlaps_aabb(&self, aabb: &Aabb) -> bool {
+    self.overlaps(&aabb.to_polygon())
+  }
+}
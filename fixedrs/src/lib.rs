@@ -1,200 +1,1100 @@
+//! Deterministic fixed-point arithmetic.
+//!
+//! # Determinism
+//!
+//! Every function in this crate that takes and returns fixed-point values is computed with integer
+//! operations only, so its result is a pure function of the raw bits of its inputs and is identical
+//! on every platform, build profile and compiler version. This is what makes the types suitable for
+//! lockstep simulation. The one exception is overflow of the plain operators (`+`, `-`, `<<`, ...),
+//! which panics in debug builds and wraps in release builds just like the primitive integers do; use
+//! the `checked_*`, `wrapping_*` or `saturating_*` families when inputs may be out of range.
+//!
+//! `from_f64` and `to_f64` are also deterministic, since they only scale by a power of two, but any
+//! float arithmetic you do before or after them is not covered by this guarantee.
+//!
+//! The exact outputs of the non-trivial functions are pinned by the golden vectors in `tests/golden`,
+//! so any change that alters a result is deliberate.
+
+pub mod checksum;
+mod decimal;
+pub mod encoding;
+mod exp_log;
+pub mod geometry;
+mod matrix;
+#[cfg(feature = "num-traits")]
+mod num;
+pub mod physics;
+mod repr;
+mod rng;
+pub mod serde;
+pub mod spatial;
+mod trig;
+mod vec3;
+
+pub use decimal::ParseFixedError;
+pub use matrix::{Q64Affine2, Q64Mat2, Q64Mat3};
+pub use repr::FixedRepr;
+pub use rng::Q64Rng;
+pub use vec3::{Q64Quat, Q64Vec3};
+
 pub const Q64_SHIFT: u32 = 32;
 
-// ==================== Fixed-point 32.32 scalar ====================
+// ==================== Fixed-point scalar ====================
 
+/// A fixed-point number stored in the integer `Repr` with `FRAC` fractional bits.
+///
+/// `Repr` may be `i32`, `i64` or `i128`. `FRAC` must leave at least four integer bits (counting
+/// the sign bit) so that `PI_TIMES_2` is representable, and seven for `RAD_TO_DEG`; using the
+/// constants or trig functions of a layout that doesn't is a compile-time error.
+///
+/// `PI`, `PI_OVER_TWO` and `PI_TIMES_2` are truncated, which keeps the original `Q64` results, while
+/// the other constants and the accurate angle functions round to nearest. For `Q64`, `PI` is one ulp
+/// below π, so `atan2`, `acos` and `to_radians` give `PI_ROUNDED` where π is meant.
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct Q64(pub i64);
+pub struct Fixed<Repr, const FRAC: u32>(pub Repr);
 
-impl Q64 {
-  pub const ZERO: Self = Self(0);
-  pub const EPSILON: Self = Self(1);
-  pub const ONE: Self = Self(1 << Q64_SHIFT);
-  pub const HALF: Self = Self(1 << (Q64_SHIFT - 1));
-  pub const PI_OVER_TWO: Self = Self(6746518852);
-  pub const PI: Self = Self(13493037704);
-  pub const PI_TIMES_2: Self = Self(26986075409);
+/// 32.32 fixed-point, the layout used by `Q64Vec`.
+pub type Q64 = Fixed<i64, Q64_SHIFT>;
 
-  pub const fn from_i32(x: i32) -> Self {
-    Self((x as i64) << Q64_SHIFT)
-  }
+/// 16.16 fixed-point.
+pub type Q32 = Fixed<i32, 16>;
 
-  pub fn to_i32(self) -> i32 {
-    (self.0 >> Q64_SHIFT) as i32
-  }
+/// A `Q64` from a decimal literal, as a string or a number, rounded to nearest with ties to even
+/// at compile time, so that tuned constants can be written readably and exactly:
+///
+/// ```
+/// use fixedrs::{q64, Q64};
+///
+/// const GRAVITY: Q64 = q64!("-9.81");
+/// const FRICTION: Q64 = q64!(0.596227);
+/// assert_eq!(GRAVITY, "-9.81".parse().unwrap());
+/// assert_eq!(FRICTION, Q64::from_bits(2560775466));
+/// ```
+///
+/// An invalid or out-of-range literal is a compile error:
+///
+/// ```compile_fail
+/// const TOO_BIG: fixedrs::Q64 = fixedrs::q64!("3e9");
+/// ```
+#[macro_export]
+macro_rules! q64 {
+  (@parse $s:expr) => {{
+    const VALUE: $crate::Q64 = $crate::Q64::from_str_const($s);
+    VALUE
+  }};
+  (- $value:literal) => {
+    $crate::q64!(@parse concat!("-", $value))
+  };
+  ($value:literal) => {
+    $crate::q64!(@parse concat!($value))
+  };
+}
 
-  pub fn from_f64(x: f64) -> Self {
-    Self((x * (1i64 << Q64_SHIFT) as f64) as i64)
-  }
+// ==================== Errors ====================
+
+/// Why a fallible operation (the `try_*` methods) failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FixedError {
+  /// The square root of a negative number.
+  NegativeSqrt,
+  /// Division by zero.
+  DivideByZero,
+  /// A divisor that was required to be positive wasn't.
+  NonPositiveDivisor,
+  /// An argument outside the domain of the function, such as `asin` of a value above one.
+  OutOfDomain,
+  /// The result doesn't fit in the type.
+  Overflow,
+  /// A float that was NaN or infinite.
+  NotFinite,
+  /// A matrix with zero determinant, which has no inverse.
+  Singular,
+}
 
-  pub fn to_f64(self) -> f64 {
-    self.0 as f64 / (1i64 << Q64_SHIFT) as f64
+impl std::fmt::Display for FixedError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.write_str(match self {
+      FixedError::NegativeSqrt => "sqrt of negative number",
+      FixedError::DivideByZero => "division by zero",
+      FixedError::NonPositiveDivisor => "rhs must be positive",
+      FixedError::OutOfDomain => "argument outside the domain of the function",
+      FixedError::Overflow => "arithmetic overflow",
+      FixedError::NotFinite => "float is not finite",
+      FixedError::Singular => "matrix is singular",
+    })
   }
+}
+
+impl std::error::Error for FixedError {}
+
+// Constants scaled by 2^CONST_SCALE and truncated, from which each layout's constants are derived.
+const CONST_SCALE: u32 = 124;
+const PI_OVER_TWO_SCALED: i128 = 33407143252030210870615011661308461978;
+const PI_SCALED: i128 = 66814286504060421741230023322616923956;
+const PI_TIMES_2_SCALED: i128 = 133628573008120843482460046645233847912;
+const E_SCALED: i128 = 57811460909138771071931939740208549692;
+const LN_2_SCALED: i128 = 14741610201594580883621508922759655274;
+const LN_10_SCALED: i128 = 48970569092555191675333655168965460618;
+const SQRT_2_SCALED: i128 = 30076996146000563943129221559703132115;
+const FRAC_1_SQRT_2_SCALED: i128 = 15038498073000281971564610779851566057;
+const FRAC_PI_4_SCALED: i128 = 16703571626015105435307505830654230989;
+// The angle conversion factors are scaled by 2^ANGLE_SCALE instead, leaving room for the integer
+// part of RAD_TO_DEG.
+const ANGLE_SCALE: u32 = 120;
+const DEG_TO_RAD_SCALED: i128 = 23199405036132090882371535875908654;
+const RAD_TO_DEG_SCALED: i128 = 76159154169108856439228128690238166517;
+const RECIP_THREE_FACT_SCALED: i128 = 3544607988759775661076818827414252202;
+const RECIP_FIVE_FACT_SCALED: i128 = 177230399437988783053840941370712610;
+const RECIP_SEVEN_FACT_SCALED: i128 = 4219771415190209120329546223112205;
+
+macro_rules! impl_fixed {
+  ($repr:ty) => {
+    impl<const FRAC: u32> Fixed<$repr, FRAC> {
+      pub const ZERO: Self = Self(0);
+      pub const EPSILON: Self = Self(1);
+      pub const ONE: Self = Self(1 << FRAC);
+      pub const HALF: Self = Self(1 << (FRAC - 1));
+      // The three π constants are truncated rather than rounded, and stay that way because the
+      // fast trig reduces by them; for `Q64`, `PI` is one ulp below the nearest value to π. The
+      // accurate angle functions round to nearest, so compare their results with the rounded
+      // `FRAC_PI_2`, `PI_ROUNDED` and `TAU` instead.
+
+      /// π/2, truncated.
+      pub const PI_OVER_TWO: Self = Self::from_scaled(PI_OVER_TWO_SCALED);
+      /// π, truncated; see `PI_ROUNDED`.
+      pub const PI: Self = Self::from_scaled(PI_SCALED);
+      /// 2π, truncated; see `TAU`.
+      pub const PI_TIMES_2: Self = Self::from_scaled(PI_TIMES_2_SCALED);
+
+      // The rest are all rounded to nearest.
+      pub const MAX: Self = Self(<$repr>::MAX);
+      pub const MIN: Self = Self(<$repr>::MIN);
+      /// The smallest positive value, the same as `EPSILON`.
+      pub const MIN_POSITIVE: Self = Self(1);
+      pub const E: Self = Self::from_scaled_rounded(E_SCALED, CONST_SCALE);
+      pub const LN_2: Self = Self::from_scaled_rounded(LN_2_SCALED, CONST_SCALE);
+      pub const LN_10: Self = Self::from_scaled_rounded(LN_10_SCALED, CONST_SCALE);
+      pub const SQRT_2: Self = Self::from_scaled_rounded(SQRT_2_SCALED, CONST_SCALE);
+      pub const FRAC_1_SQRT_2: Self = Self::from_scaled_rounded(FRAC_1_SQRT_2_SCALED, CONST_SCALE);
+      /// π, rounded to nearest, unlike `PI`.
+      pub const PI_ROUNDED: Self = Self::from_scaled_rounded(PI_SCALED, CONST_SCALE);
+      /// 2π, rounded to nearest, which for `Q64` is also `PI_TIMES_2`.
+      pub const TAU: Self = Self::from_scaled_rounded(PI_TIMES_2_SCALED, CONST_SCALE);
+      /// π/2, rounded to nearest, which for `Q64` is also `PI_OVER_TWO`.
+      pub const FRAC_PI_2: Self = Self::from_scaled_rounded(PI_OVER_TWO_SCALED, CONST_SCALE);
+      pub const FRAC_PI_4: Self = Self::from_scaled_rounded(FRAC_PI_4_SCALED, CONST_SCALE);
+      /// π/180, the number of radians in a degree.
+      pub const DEG_TO_RAD: Self = Self::from_scaled_rounded(DEG_TO_RAD_SCALED, ANGLE_SCALE);
+      /// 180/π, the number of degrees in a radian.
+      pub const RAD_TO_DEG: Self = Self::from_scaled_rounded(RAD_TO_DEG_SCALED, ANGLE_SCALE);
+
+      const fn from_scaled(x: i128) -> Self {
+        assert!(FRAC + 4 <= <$repr>::BITS, "too few integer bits");
+        Self((x >> (CONST_SCALE - FRAC)) as $repr)
+      }
+
+      /// Rounds a constant scaled by 2^scale to nearest. The scaled constants carry far more bits
+      /// than any layout keeps, so the result is the nearest representable value.
+      const fn from_scaled_rounded(x: i128, scale: u32) -> Self {
+        assert!(FRAC < scale, "too many fractional bits");
+        let rounded = ((x >> (scale - FRAC - 1)) + 1) >> 1;
+        assert!(rounded == rounded as $repr as i128, "too few integer bits");
+        Self(rounded as $repr)
+      }
+
+      pub const fn from_bits(bits: $repr) -> Self {
+        Self(bits)
+      }
+
+      pub const fn to_bits(self) -> $repr {
+        self.0
+      }
+
+      pub const fn to_le_bytes(self) -> [u8; std::mem::size_of::<$repr>()] {
+        self.0.to_le_bytes()
+      }
+
+      pub const fn from_le_bytes(bytes: [u8; std::mem::size_of::<$repr>()]) -> Self {
+        Self(<$repr>::from_le_bytes(bytes))
+      }
+
+      pub const fn from_i32(x: i32) -> Self {
+        Self((x as $repr) << FRAC)
+      }
+
+      /// Parses like `FromStr`, but is const, so that constants can be written as decimals; see also
+      /// `q64!`. Panics if `s` isn't a literal in range, which in a constant is a compile error.
+      pub const fn from_str_const(s: &str) -> Self {
+        match Self::parse_decimal(s) {
+          Ok(x) => x,
+          Err(ParseFixedError::Invalid) => panic!("invalid fixed-point literal"),
+          Err(ParseFixedError::Overflow) => panic!("fixed-point literal out of range"),
+        }
+      }
+
+      const fn parse_decimal(s: &str) -> Result<Self, ParseFixedError> {
+        let (negative, magnitude) = match decimal::parse(s.as_bytes(), FRAC) {
+          Ok(parsed) => parsed,
+          Err(e) => return Err(e),
+        };
+        let limit = (<$repr>::MAX as u128) + negative as u128;
+        if magnitude > limit {
+          return Err(ParseFixedError::Overflow);
+        }
+        Ok(Self(match negative {
+          true => (magnitude as $repr).wrapping_neg(),
+          false => magnitude as $repr,
+        }))
+      }
+
+      // Const versions of the arithmetic operators, for computing constants. They round like the
+      // operators, and panic on overflow, which in a constant is a compile error. The products and
+      // quotients widen to i128 for every layout, since the FixedRepr methods can't be const.
+
+      pub const fn const_add(self, rhs: Self) -> Self {
+        match self.0.checked_add(rhs.0) {
+          Some(x) => Self(x),
+          None => panic!("overflow in const_add"),
+        }
+      }
+
+      pub const fn const_sub(self, rhs: Self) -> Self {
+        match self.0.checked_sub(rhs.0) {
+          Some(x) => Self(x),
+          None => panic!("overflow in const_sub"),
+        }
+      }
+
+      pub const fn const_neg(self) -> Self {
+        match self.0.checked_neg() {
+          Some(x) => Self(x),
+          None => panic!("overflow in const_neg"),
+        }
+      }
+
+      pub const fn const_mul(self, rhs: Self) -> Self {
+        let (x, overflow) = repr::overflowing_mul_shr_i128(self.0 as i128, rhs.0 as i128, FRAC);
+        if overflow || x != x as $repr as i128 {
+          panic!("overflow in const_mul");
+        }
+        Self(x as $repr)
+      }
+
+      /// Panics if `rhs` is zero, like `/`.
+      pub const fn const_div(self, rhs: Self) -> Self {
+        let (x, overflow) = repr::overflowing_shl_div_i128(self.0 as i128, FRAC, rhs.0 as i128);
+        if overflow || x != x as $repr as i128 {
+          panic!("overflow in const_div");
+        }
+        Self(x as $repr)
+      }
+
+      /// Rounds toward negative infinity, like `to_i32_floor`.
+      pub fn to_i32(self) -> i32 {
+        (self.0 >> FRAC) as i32
+      }
+
+      // The to_i32_* family wraps if the integer part doesn't fit in an i32, like `to_i32`.
+
+      pub fn to_i32_floor(self) -> i32 {
+        self.to_i32()
+      }
+
+      pub fn to_i32_ceil(self) -> i32 {
+        ((self.0 >> FRAC) + (self.fract_bits() != 0) as $repr) as i32
+      }
+
+      pub fn to_i32_trunc(self) -> i32 {
+        ((self.0 >> FRAC) + (self.fract_bits() != 0 && self.0 < 0) as $repr) as i32
+      }
+
+      /// Rounds to nearest, with ties away from zero like `f64::round`.
+      pub fn to_i32_round(self) -> i32 {
+        let fract = self.fract_bits();
+        let round_up = fract > Self::HALF.0 || (fract == Self::HALF.0 && self.0 > 0);
+        ((self.0 >> FRAC) + round_up as $repr) as i32
+      }
+
+      /// The fractional bits of the raw value, which are the distance above the floor.
+      fn fract_bits(self) -> $repr {
+        self.0 & (Self::ONE.0 - 1)
+      }
+
+      /// Truncates toward zero, saturating out-of-range values and mapping NaN to zero.
+      pub const fn from_f64(x: f64) -> Self {
+        Self((x * ((1 as $repr) << FRAC) as f64) as $repr)
+      }
+
+      /// Rounds to the nearest representable value, with ties to even. Out-of-range values
+      /// saturate and NaN maps to zero; see `try_from_f64`.
+      pub fn from_f64_round(x: f64) -> Self {
+        Self((x * ((1 as $repr) << FRAC) as f64).round_ties_even() as $repr)
+      }
+
+      /// Rounds like `from_f64_round`, but rejects NaN, infinities and values out of range.
+      pub fn try_from_f64(x: f64) -> Result<Self, FixedError> {
+        if !x.is_finite() {
+          return Err(FixedError::NotFinite);
+        }
+        let scaled = (x * ((1 as $repr) << FRAC) as f64).round_ties_even();
+        // MIN is a power of two, so it and its negation are exact as floats.
+        let min = <$repr>::MIN as f64;
+        match scaled >= min && scaled < -min {
+          true => Ok(Self(scaled as $repr)),
+          false => Err(FixedError::Overflow),
+        }
+      }
+
+      /// Correctly rounded, since scaling by a power of two is exact.
+      pub fn to_f64(self) -> f64 {
+        self.0 as f64 / ((1 as $repr) << FRAC) as f64
+      }
+
+      /// Truncates toward zero like `from_f64`. Every f32 is exactly an f64, so this is exactly
+      /// `from_f64(x as f64)`.
+      pub fn from_f32(x: f32) -> Self {
+        Self::from_f64(x as f64)
+      }
+
+      pub fn from_f32_round(x: f32) -> Self {
+        Self::from_f64_round(x as f64)
+      }
+
+      pub fn try_from_f32(x: f32) -> Result<Self, FixedError> {
+        Self::try_from_f64(x as f64)
+      }
+
+      /// Correctly rounded, rather than rounding twice through `to_f64`.
+      pub fn to_f32(self) -> f32 {
+        self.0 as f32 / ((1 as $repr) << FRAC) as f32
+      }
+
+      /// Rounds toward zero, so the result is the largest representable value whose square is at most
+      /// `self`, up to the precision lost when `self` is too large to be shifted left by `FRAC`.
+      /// Panics on negative input; see `try_sqrt`.
+      pub fn sqrt(self) -> Self {
+        self.try_sqrt().unwrap_or_else(|e| panic!("{}", e))
+      }
+
+      pub fn try_sqrt(self) -> Result<Self, FixedError> {
+        use num_integer::Roots;
+        if self.0 < 0 {
+          return Err(FixedError::NegativeSqrt);
+        }
+        // Preserve as much precision as possible by shifting the input to the left as far as possible.
+        // The shift must have the same parity as FRAC, so that we can halve the difference below.
+        let mut available_left_shift = self.0.leading_zeros() as i32 - 1;
+        if (available_left_shift - FRAC as i32) % 2 != 0 {
+          available_left_shift -= 1;
+        }
+        let x = match available_left_shift < 0 {
+          true => (self.0 >> 1).sqrt(),
+          false => (self.0 << available_left_shift).sqrt(),
+        };
+        let final_shift_right = (available_left_shift - FRAC as i32) / 2;
+        if final_shift_right < 0 {
+          return Ok(Self(x << -final_shift_right));
+        }
+        Ok(Self(x >> final_shift_right))
+      }
+
+      /// Converts radians to degrees, rounded to nearest. Wraps on overflow, like `*`.
+      pub fn to_degrees(self) -> Self {
+        self.mul_scaled_rounded(RAD_TO_DEG_SCALED, ANGLE_SCALE)
+      }
+
+      /// Converts degrees to radians, rounded to nearest.
+      pub fn to_radians(self) -> Self {
+        self.mul_scaled_rounded(DEG_TO_RAD_SCALED, ANGLE_SCALE)
+      }
+
+      /// Multiplies by a constant scaled by 2^scale, with all of its bits rather than those of the
+      /// rounded constant, so that the product is rounded only once.
+      fn mul_scaled_rounded(self, x: i128, scale: u32) -> Self {
+        let (product, _) = repr::overflowing_mul_shr_i128(self.0 as i128, x, scale - 1);
+        Self((product.wrapping_add(1) >> 1) as $repr)
+      }
+
+      /// Accurate to within about half an ulp for layouts with up to 62 fractional bits, and to about
+      /// 2^-62 beyond that. The argument reduction is exact for any input below 2^64 in magnitude.
+      pub fn sin(self) -> Self {
+        let (quadrant, r) = trig::reduce_q62(self.to_q62_angle());
+        Self(trig::from_q62(trig::sin_quadrant_q62(quadrant, r) as i128, FRAC) as $repr)
+      }
+
+      /// Accurate to the same precision as `sin`.
+      pub fn cos(self) -> Self {
+        let (quadrant, r) = trig::reduce_q62(self.to_q62_angle());
+        Self(trig::from_q62(trig::sin_quadrant_q62((quadrant + 1) % 4, r) as i128, FRAC) as $repr)
+      }
+
+      /// Returns `(self.sin(), self.cos())`, bit-identical to calling them separately, but doing the
+      /// argument reduction and squaring only once.
+      pub fn sin_cos(self) -> (Self, Self) {
+        let (quadrant, r) = trig::reduce_q62(self.to_q62_angle());
+        let (sin, cos) = trig::sin_cos_quadrant_q62(quadrant, r);
+        (Self(trig::from_q62(sin as i128, FRAC) as $repr), Self(trig::from_q62(cos as i128, FRAC) as $repr))
+      }
+
+      /// The angle of the point `(x, self)` in (-π, π], accurate to within about half an ulp like
+      /// `sin`, so the angle of the negative x axis is `PI_ROUNDED`, not the truncated `PI`.
+      /// `ZERO.atan2(ZERO)` is zero.
+      pub fn atan2(self, x: Self) -> Self {
+        Self(trig::from_q62(trig::atan2_q62(self.0 as i128, x.0 as i128), FRAC) as $repr)
+      }
+
+      /// The arctangent in [-FRAC_PI_2, FRAC_PI_2].
+      pub fn atan(self) -> Self {
+        self.atan2(Self::ONE)
+      }
+
+      /// The arcsine in [-FRAC_PI_2, FRAC_PI_2]. Panics if `self` is outside [-1, 1]; see `try_asin`.
+      pub fn asin(self) -> Self {
+        self.try_asin().unwrap_or_else(|e| panic!("{}", e))
+      }
+
+      pub fn try_asin(self) -> Result<Self, FixedError> {
+        let x = self.to_q62_unit()?;
+        let c = trig::sqrt_one_minus_square_q62(x);
+        Ok(Self(trig::from_q62(trig::atan2_q62(x as i128, c as i128), FRAC) as $repr))
+      }
+
+      /// The arccosine in [0, PI_ROUNDED]. Panics if `self` is outside [-1, 1]; see `try_acos`.
+      pub fn acos(self) -> Self {
+        self.try_acos().unwrap_or_else(|e| panic!("{}", e))
+      }
+
+      pub fn try_acos(self) -> Result<Self, FixedError> {
+        let x = self.to_q62_unit()?;
+        let s = trig::sqrt_one_minus_square_q62(x);
+        Ok(Self(trig::from_q62(trig::atan2_q62(s as i128, x as i128), FRAC) as $repr))
+      }
+
+      fn to_q62_unit(self) -> Result<i64, FixedError> {
+        if self > Self::ONE || self < -Self::ONE {
+          return Err(FixedError::OutOfDomain);
+        }
+        Ok((match FRAC >= trig::Q62_SHIFT {
+          true => self.0 as i128 >> (FRAC - trig::Q62_SHIFT),
+          false => (self.0 as i128) << (trig::Q62_SHIFT - FRAC),
+        }) as i64)
+      }
+
+      /// e^self, rounded to nearest. Returns None if the result overflows; results too small to
+      /// represent round to zero.
+      pub fn exp(self) -> Option<Self> {
+        Self::from_exp2_q62(exp_log::exp_to_exp2_q62(self.to_q62_saturating()))
+      }
+
+      /// 2^self, rounded to nearest, with the same domain handling as `exp`.
+      pub fn exp2(self) -> Option<Self> {
+        Self::from_exp2_q62(self.to_q62_saturating())
+      }
+
+      /// The natural logarithm, rounded to nearest. Returns None if `self` is not positive.
+      pub fn ln(self) -> Option<Self> {
+        match self.0 > 0 {
+          true => Some(Self(trig::from_q62(exp_log::ln_q62(self.0 as u128, FRAC), FRAC) as $repr)),
+          false => None,
+        }
+      }
+
+      /// The base-2 logarithm, rounded to nearest. Returns None if `self` is not positive.
+      pub fn log2(self) -> Option<Self> {
+        match self.0 > 0 {
+          true => Some(Self(trig::from_q62(exp_log::log2_q62(self.0 as u128, FRAC), FRAC) as $repr)),
+          false => None,
+        }
+      }
+
+      /// `self` to an integer power by repeated squaring, so exact up to the rounding of each
+      /// multiplication. For negative `n`, the power is carried to 127 significant bits before
+      /// taking its reciprocal, which is rounded toward zero like `/`: it is exact whenever
+      /// representable, and within an ulp otherwise. Returns None on overflow, or for `ZERO` to a
+      /// negative power.
+      pub fn powi(self, n: i32) -> Option<Self> {
+        if n >= 0 {
+          return self.powi_unsigned(n as u32);
+        }
+        if self.0 == 0 {
+          return None;
+        }
+        let negative = self.0 < 0 && n % 2 != 0;
+        let limit = <$repr>::MAX as u128 + negative as u128;
+        let magnitude = exp_log::recip_powi((self.0 as i128).unsigned_abs(), FRAC, n.unsigned_abs(), limit)?;
+        Some(Self(match negative {
+          true => (magnitude as $repr).wrapping_neg(),
+          false => magnitude as $repr,
+        }))
+      }
+
+      fn powi_unsigned(self, mut n: u32) -> Option<Self> {
+        let mut result = Self::ONE;
+        let mut base = self;
+        loop {
+          if n & 1 == 1 {
+            result = result.checked_mul(base)?;
+          }
+          n >>= 1;
+          if n == 0 {
+            return Some(result);
+          }
+          base = base.checked_mul(base)?;
+        }
+      }
+
+      /// `self` to a fractional power, computed as `exp2(y * log2(self))`. `ZERO.powf(y)` is `ZERO`
+      /// for positive `y` and `ONE` for zero `y`. Returns None on overflow, for negative `self`, or
+      /// for `ZERO` to a negative power; use `powi` for integer powers of negative numbers.
+      pub fn powf(self, y: Self) -> Option<Self> {
+        match self.0 {
+          0 if y.0 > 0 => Some(Self::ZERO),
+          0 if y.0 == 0 => Some(Self::ONE),
+          x if x <= 0 => None,
+          x => Self::from_exp2_q62(exp_log::mul_q62_saturating(
+            y.to_q62_saturating(),
+            exp_log::log2_q62(x as u128, FRAC),
+          )),
+        }
+      }
+
+      fn from_exp2_q62(t: i128) -> Option<Self> {
+        exp_log::exp2_q62(t, FRAC).and_then(|x| <$repr>::try_from(x).ok()).map(Self)
+      }
+
+      /// Converts to an i128 with 62 fractional bits, saturating at +-2^60.
+      fn to_q62_saturating(self) -> i128 {
+        match FRAC >= trig::Q62_SHIFT {
+          true => {
+            let limit = 1i128 << (60 + trig::Q62_SHIFT);
+            ((self.0 as i128) >> (FRAC - trig::Q62_SHIFT)).clamp(-limit, limit)
+          }
+          false => {
+            let limit = 1i128 << (60 + FRAC);
+            (self.0 as i128).clamp(-limit, limit) << (trig::Q62_SHIFT - FRAC)
+          }
+        }
+      }
+
+      /// Converts to an i128 with 62 fractional bits, first reducing modulo `PI_TIMES_2` (inexactly)
+      /// if the value is too large for that.
+      fn to_q62_angle(self) -> i128 {
+        let raw = match (self.0 as i128).unsigned_abs() >> FRAC < 1 << 63 {
+          true => self.0,
+          false => self.0.rem_euclid(Self::PI_TIMES_2.0),
+        } as i128;
+        match FRAC >= trig::Q62_SHIFT {
+          true => raw >> (FRAC - trig::Q62_SHIFT),
+          false => raw << (trig::Q62_SHIFT - FRAC),
+        }
+      }
+
+      /// The cheaper 4-term Taylor series, accurate to roughly 1e-3. Reduces `self` modulo
+      /// `PI_TIMES_2` exactly, then evaluates a fixed polynomial, so it is just as deterministic as `sin`.
+      /// For `Q64` it is bit-identical to the original `Q64::sin`, which the accurate `sin` replaced.
+      pub fn sin_fast(self) -> Self {
+        let mut x = Self(self.0.rem_euclid(Self::PI_TIMES_2.0));
+        let mut negate = false;
+        if x > Self::PI {
+          x -= Self::PI;
+          negate = true;
+        }
+        if x > Self::PI_OVER_TWO {
+          x = Self::PI - x;
+        }
+        // Taylor series to x^7, summed term by term like the original `Q64::sin`, so that `Q64`
+        // keeps its exact bits. x^7 reaches about 23.5, so layouts with fewer than six integer bits
+        // use Horner form instead, where no intermediate exceeds PI_TIMES_2.
+        let recip_three_fact = Self::from_scaled_rounded(RECIP_THREE_FACT_SCALED, CONST_SCALE);
+        let recip_five_fact = Self::from_scaled_rounded(RECIP_FIVE_FACT_SCALED, CONST_SCALE);
+        let recip_seven_fact = Self::from_scaled_rounded(RECIP_SEVEN_FACT_SCALED, CONST_SCALE);
+        let x2 = x * x;
+        let mut y = match FRAC + 6 <= <$repr>::BITS {
+          true => {
+            let x3 = x2 * x;
+            let x5 = x3 * x2;
+            let x7 = x5 * x2;
+            x - x3 * recip_three_fact + x5 * recip_five_fact - x7 * recip_seven_fact
+          }
+          false => x - x * x2 * (recip_three_fact - x2 * (recip_five_fact - x2 * recip_seven_fact)),
+        };
+        if negate {
+          y = -y;
+        }
+        y
+      }
+
+      /// Computed as `sin_fast(PI_OVER_TWO - self)`, like the original `Q64::cos`, with the same
+      /// accuracy as `sin_fast`.
+      pub fn cos_fast(self) -> Self {
+        (Self::PI_OVER_TWO - self).sin_fast()
+      }
+
+      pub fn abs(self) -> Self {
+        Self(self.0.abs())
+      }
+
+      // The rounding family follows the f64 methods of the same names. The ones that round up
+      // overflow like `+` does for values within one of MAX. `min`, `max` and `clamp` come from Ord.
+
+      pub fn floor(self) -> Self {
+        Self(self.0 - self.fract_bits())
+      }
+
+      pub fn ceil(self) -> Self {
+        match self.fract_bits() {
+          0 => self,
+          fract => Self(self.0 - fract) + Self::ONE,
+        }
+      }
+
+      pub fn trunc(self) -> Self {
+        match self.0 < 0 && self.fract_bits() != 0 {
+          true => self.floor() + Self::ONE,
+          false => self.floor(),
+        }
+      }
+
+      /// Rounds to the nearest integer, with ties away from zero.
+      pub fn round(self) -> Self {
+        let fract = self.fract_bits();
+        match fract > Self::HALF.0 || (fract == Self::HALF.0 && self.0 > 0) {
+          true => self.floor() + Self::ONE,
+          false => self.floor(),
+        }
+      }
+
+      /// Rounds to the nearest integer, with ties to the even one.
+      pub fn round_ties_even(self) -> Self {
+        let floor = self.floor();
+        let fract = self.fract_bits();
+        let floor_is_odd = floor.0 & Self::ONE.0 != 0;
+        match fract > Self::HALF.0 || (fract == Self::HALF.0 && floor_is_odd) {
+          true => floor + Self::ONE,
+          false => floor,
+        }
+      }
+
+      /// `self - self.trunc()`, so it has the sign of `self`.
+      pub fn fract(self) -> Self {
+        self - self.trunc()
+      }
+
+      /// `ONE`, `ZERO` or `-ONE` according to the sign of `self`.
+      pub fn signum(self) -> Self {
+        match self.0 {
+          0 => Self::ZERO,
+          x if x < 0 => -Self::ONE,
+          _ => Self::ONE,
+        }
+      }
+
+      pub fn is_negative(self) -> bool {
+        self.0 < 0
+      }
+
+      pub fn is_positive(self) -> bool {
+        self.0 > 0
+      }
+
+      /// `self + (other - self) * t`, so exact at `t = ZERO` and `t = ONE`.
+      pub fn lerp(self, other: Self, t: Self) -> Self {
+        self + (other - self) * t
+      }
+
+      /// The least nonnegative remainder of `self` modulo `rhs`, which is exact. Panics if `rhs` is zero.
+      pub fn rem_euclid(self, rhs: Self) -> Self {
+        Self(self.0.rem_euclid(rhs.0))
+      }
+
+      /// `self / rhs` rounded toward positive infinity, for negative `self` too. Panics if `rhs` isn't
+      /// positive, and wraps on overflow; see `try_ceiling_div_by_positive`.
+      pub fn ceiling_div_by_positive(self, rhs: Self) -> Self {
+        if rhs.0 <= 0 {
+          panic!("{}", FixedError::NonPositiveDivisor);
+        }
+        Self(self.0.overflowing_shl_div_ceil(FRAC, rhs.0).0)
+      }
+
+      pub fn try_ceiling_div_by_positive(self, rhs: Self) -> Result<Self, FixedError> {
+        if rhs.0 <= 0 {
+          return Err(FixedError::NonPositiveDivisor);
+        }
+        match self.0.overflowing_shl_div_ceil(FRAC, rhs.0) {
+          (x, false) => Ok(Self(x)),
+          (_, true) => Err(FixedError::Overflow),
+        }
+      }
+
+      /// Like `checked_div`, but distinguishes division by zero from overflow.
+      pub fn try_div(self, rhs: Self) -> Result<Self, FixedError> {
+        if rhs.0 == 0 {
+          return Err(FixedError::DivideByZero);
+        }
+        match self.overflowing_div(rhs) {
+          (x, false) => Ok(x),
+          (_, true) => Err(FixedError::Overflow),
+        }
+      }
+
+      // The overflowing, checked, wrapping and saturating families mirror the ones on the primitive
+      // integers. Division by zero panics in all of them except checked_div, which returns None.
+
+      pub fn overflowing_add(self, rhs: Self) -> (Self, bool) {
+        let (x, overflow) = self.0.overflowing_add(rhs.0);
+        (Self(x), overflow)
+      }
+
+      pub fn overflowing_sub(self, rhs: Self) -> (Self, bool) {
+        let (x, overflow) = self.0.overflowing_sub(rhs.0);
+        (Self(x), overflow)
+      }
+
+      pub fn overflowing_mul(self, rhs: Self) -> (Self, bool) {
+        let (x, overflow) = self.0.overflowing_mul_shr(rhs.0, FRAC);
+        (Self(x), overflow)
+      }
+
+      pub fn overflowing_div(self, rhs: Self) -> (Self, bool) {
+        let (x, overflow) = self.0.overflowing_shl_div(FRAC, rhs.0);
+        (Self(x), overflow)
+      }
+
+      pub fn checked_add(self, rhs: Self) -> Option<Self> {
+        self.0.checked_add(rhs.0).map(Self)
+      }
+
+      pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+        self.0.checked_sub(rhs.0).map(Self)
+      }
+
+      pub fn checked_mul(self, rhs: Self) -> Option<Self> {
+        match self.overflowing_mul(rhs) {
+          (x, false) => Some(x),
+          (_, true) => None,
+        }
+      }
+
+      pub fn checked_div(self, rhs: Self) -> Option<Self> {
+        self.try_div(rhs).ok()
+      }
+
+      pub fn checked_neg(self) -> Option<Self> {
+        self.0.checked_neg().map(Self)
+      }
+
+      pub fn checked_abs(self) -> Option<Self> {
+        self.0.checked_abs().map(Self)
+      }
+
+      pub fn checked_shl(self, shift: u32) -> Option<Self> {
+        self.0.checked_shl(shift).map(Self)
+      }
+
+      pub fn checked_shr(self, shift: u32) -> Option<Self> {
+        self.0.checked_shr(shift).map(Self)
+      }
+
+      pub fn wrapping_add(self, rhs: Self) -> Self {
+        Self(self.0.wrapping_add(rhs.0))
+      }
+
+      pub fn wrapping_sub(self, rhs: Self) -> Self {
+        Self(self.0.wrapping_sub(rhs.0))
+      }
+
+      pub fn wrapping_mul(self, rhs: Self) -> Self {
+        self.overflowing_mul(rhs).0
+      }
 
-  pub fn sqrt(self) -> Self {
-    use num_integer::Roots;
-    if self.0 < 0 {
-      panic!("sqrt of negative number");
+      pub fn wrapping_div(self, rhs: Self) -> Self {
+        self.overflowing_div(rhs).0
+      }
+
+      pub fn saturating_add(self, rhs: Self) -> Self {
+        Self(self.0.saturating_add(rhs.0))
+      }
+
+      pub fn saturating_sub(self, rhs: Self) -> Self {
+        Self(self.0.saturating_sub(rhs.0))
+      }
+
+      pub fn saturating_mul(self, rhs: Self) -> Self {
+        match self.overflowing_mul(rhs) {
+          (x, false) => x,
+          (_, true) => Self::saturated((self.0 < 0) != (rhs.0 < 0)),
+        }
+      }
+
+      pub fn saturating_div(self, rhs: Self) -> Self {
+        match self.overflowing_div(rhs) {
+          (x, false) => x,
+          (_, true) => Self::saturated((self.0 < 0) != (rhs.0 < 0)),
+        }
+      }
+
+      fn saturated(negative: bool) -> Self {
+        match negative {
+          true => Self::MIN,
+          false => Self::MAX,
+        }
+      }
     }
-    // Preserve as much precision as possible by shifting the input to the left as far as possible.
-    let available_left_shift = ((self.0.leading_zeros() as u32).saturating_sub(1) / 2) * 2;
-    let x = (self.0 << available_left_shift).sqrt();
-    let final_shift_right = (available_left_shift as i32 - Q64_SHIFT as i32) / 2;
-    if final_shift_right < 0 {
-      return Self(x << -final_shift_right);
+
+    /// Exact. Using this with a layout that has fewer than 16 integer bits is a compile-time error.
+    impl<const FRAC: u32> From<i16> for Fixed<$repr, FRAC> {
+      fn from(x: i16) -> Self {
+        const { assert!(FRAC + 16 <= <$repr>::BITS, "too few integer bits for i16") };
+        Self((x as $repr) << FRAC)
+      }
     }
-    Self(x >> final_shift_right)
-  }
 
-  pub fn sin(self) -> Self {
-    let mut x = Self(self.0.rem_euclid(Self::PI_TIMES_2.0));
-    let mut negate = false;
-    if x > Self::PI {
-      x -= Self::PI;
-      negate = true;
+    impl<const FRAC: u32> TryFrom<i64> for Fixed<$repr, FRAC> {
+      type Error = FixedError;
+      fn try_from(x: i64) -> Result<Self, FixedError> {
+        let (min, max) = ((<$repr>::MIN >> FRAC) as i128, (<$repr>::MAX >> FRAC) as i128);
+        match (min..=max).contains(&(x as i128)) {
+          true => Ok(Self((x as $repr) << FRAC)),
+          false => Err(FixedError::Overflow),
+        }
+      }
     }
-    if x > Self::PI_OVER_TWO {
-      x = Self::PI - x;
+
+    impl<const FRAC: u32> std::ops::Neg for Fixed<$repr, FRAC> {
+      type Output = Self;
+      fn neg(self) -> Self {
+        Self(-self.0)
+      }
     }
-    let x2 = x * x;
-    let x3 = x2 * x;
-    let x5 = x3 * x2;
-    let x7 = x5 * x2;
-    const RECIP_THREE_FACT: Q64 = Q64(715827883);
-    const RECIP_FIVE_FACT: Q64 = Q64(35791394);
-    const RECIP_SEVEN_FACT: Q64 = Q64(852176);
-    let mut y = x - x3 * RECIP_THREE_FACT + x5 * RECIP_FIVE_FACT - x7 * RECIP_SEVEN_FACT;
-    if negate {
-      y = -y;
+
+    impl<const FRAC: u32> std::ops::Add for Fixed<$repr, FRAC> {
+      type Output = Self;
+      fn add(self, rhs: Self) -> Self {
+        Self(self.0 + rhs.0)
+      }
     }
-    y
-  }
 
-  pub fn cos(self) -> Self {
-    (Self::PI_OVER_TWO - self).sin()
-  }
+    impl<const FRAC: u32> std::ops::Sub for Fixed<$repr, FRAC> {
+      type Output = Self;
+      fn sub(self, rhs: Self) -> Self {
+        Self(self.0 - rhs.0)
+      }
+    }
 
-  pub fn angle_to_vec(self) -> Q64Vec {
-    // FIXME: I can compute these two simultaneously more efficiently.
-    Q64Vec {
-      x: self.cos(),
-      y: self.sin(),
+    impl<const FRAC: u32> std::ops::Mul for Fixed<$repr, FRAC> {
+      type Output = Self;
+      fn mul(self, rhs: Self) -> Self {
+        Self(self.0.overflowing_mul_shr(rhs.0, FRAC).0)
+      }
     }
-  }
 
-  pub fn abs(self) -> Self {
-    Self(self.0.abs())
-  }
+    impl<const FRAC: u32> std::ops::Div for Fixed<$repr, FRAC> {
+      type Output = Self;
+      fn div(self, rhs: Self) -> Self {
+        Self(self.0.overflowing_shl_div(FRAC, rhs.0).0)
+      }
+    }
 
-  pub fn ceiling_div_by_positive(self, rhs: Self) -> Self {
-    if rhs.0 <= 0 {
-      panic!("rhs must be positive");
+    /// The remainder of truncating division, with the sign of `self`, which is exact. Panics if
+    /// `rhs` is zero; see `rem_euclid` for the nonnegative remainder.
+    impl<const FRAC: u32> std::ops::Rem for Fixed<$repr, FRAC> {
+      type Output = Self;
+      fn rem(self, rhs: Self) -> Self {
+        Self(self.0 % rhs.0)
+      }
     }
-    let lhs = (self.0 as i128) << Q64_SHIFT;
-    Self(((lhs + rhs.0 as i128 - 1) / rhs.0 as i128) as i64)
-  }
-}
 
-impl std::ops::Neg for Q64 {
-  type Output = Self;
-  fn neg(self) -> Self {
-    Self(-self.0)
-  }
-}
+    impl<const FRAC: u32> std::ops::Shl<u32> for Fixed<$repr, FRAC> {
+      type Output = Self;
+      fn shl(self, shift: u32) -> Self {
+        Self(self.0 << shift)
+      }
+    }
 
-impl std::ops::Add for Q64 {
-  type Output = Self;
-  fn add(self, rhs: Self) -> Self {
-    Self(self.0 + rhs.0)
-  }
-}
+    impl<const FRAC: u32> std::ops::Shr<u32> for Fixed<$repr, FRAC> {
+      type Output = Self;
+      fn shr(self, shift: u32) -> Self {
+        Self(self.0 >> shift)
+      }
+    }
 
-impl std::ops::Sub for Q64 {
-  type Output = Self;
-  fn sub(self, rhs: Self) -> Self {
-    Self(self.0 - rhs.0)
-  }
-}
+    impl<const FRAC: u32> std::ops::AddAssign for Fixed<$repr, FRAC> {
+      fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+      }
+    }
 
-impl std::ops::Mul for Q64 {
-  type Output = Self;
-  fn mul(self, rhs: Self) -> Self {
-    Self(((self.0 as i128 * rhs.0 as i128) >> Q64_SHIFT) as i64)
-  }
-}
+    impl<const FRAC: u32> std::ops::SubAssign for Fixed<$repr, FRAC> {
+      fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+      }
+    }
 
-impl std::ops::Div for Q64 {
-  type Output = Self;
-  fn div(self, rhs: Self) -> Self {
-    Self((((self.0 as i128) << Q64_SHIFT) / rhs.0 as i128) as i64)
-  }
-}
+    impl<const FRAC: u32> std::ops::MulAssign for Fixed<$repr, FRAC> {
+      fn mul_assign(&mut self, rhs: Self) {
+        *self = *self * rhs;
+      }
+    }
 
-impl std::ops::Shl<u32> for Q64 {
-  type Output = Self;
-  fn shl(self, shift: u32) -> Self {
-    Self(self.0 << shift)
-  }
-}
+    impl<const FRAC: u32> std::ops::DivAssign for Fixed<$repr, FRAC> {
+      fn div_assign(&mut self, rhs: Self) {
+        *self = *self / rhs;
+      }
+    }
 
-impl std::ops::Shr<u32> for Q64 {
-  type Output = Self;
-  fn shr(self, shift: u32) -> Self {
-    Self(self.0 >> shift)
-  }
-}
+    impl<const FRAC: u32> std::ops::RemAssign for Fixed<$repr, FRAC> {
+      fn rem_assign(&mut self, rhs: Self) {
+        *self = *self % rhs;
+      }
+    }
 
-impl std::ops::AddAssign for Q64 {
-  fn add_assign(&mut self, rhs: Self) {
-    *self = *self + rhs;
-  }
-}
+    impl<const FRAC: u32> std::ops::ShlAssign<u32> for Fixed<$repr, FRAC> {
+      fn shl_assign(&mut self, shift: u32) {
+        *self = *self << shift;
+      }
+    }
 
-impl std::ops::SubAssign for Q64 {
-  fn sub_assign(&mut self, rhs: Self) {
-    *self = *self - rhs;
-  }
-}
+    impl<const FRAC: u32> std::ops::ShrAssign<u32> for Fixed<$repr, FRAC> {
+      fn shr_assign(&mut self, shift: u32) {
+        *self = *self >> shift;
+      }
+    }
 
-impl std::ops::MulAssign for Q64 {
-  fn mul_assign(&mut self, rhs: Self) {
-    *self = *self * rhs;
-  }
-}
+    impl<const FRAC: u32> std::iter::Sum for Fixed<$repr, FRAC> {
+      fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::ZERO, |a, b| a + b)
+      }
+    }
 
-impl std::ops::DivAssign for Q64 {
-  fn div_assign(&mut self, rhs: Self) {
-    *self = *self / rhs;
-  }
-}
+    impl<'a, const FRAC: u32> std::iter::Sum<&'a Self> for Fixed<$repr, FRAC> {
+      fn sum<I: Iterator<Item = &'a Self>>(iter: I) -> Self {
+        iter.copied().sum()
+      }
+    }
 
-impl std::ops::ShlAssign<u32> for Q64 {
-  fn shl_assign(&mut self, shift: u32) {
-    *self = *self << shift;
-  }
+    impl<const FRAC: u32> std::iter::Product for Fixed<$repr, FRAC> {
+      fn product<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::ONE, |a, b| a * b)
+      }
+    }
+
+    impl<'a, const FRAC: u32> std::iter::Product<&'a Self> for Fixed<$repr, FRAC> {
+      fn product<I: Iterator<Item = &'a Self>>(iter: I) -> Self {
+        iter.copied().product()
+      }
+    }
+
+    /// Prints the exact decimal value rounded to the requested precision, or without one, the
+    /// shortest decimal that parses back to the same value.
+    impl<const FRAC: u32> std::fmt::Display for Fixed<$repr, FRAC> {
+      fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = decimal::format((self.0 as i128).unsigned_abs(), FRAC, f.precision());
+        f.pad_integral(self.0 >= 0, "", &s)
+      }
+    }
+
+    /// Parses decimal literals such as `-12.375` or `1e-3`, rounding exactly to the nearest
+    /// representable value with ties to even.
+    impl<const FRAC: u32> std::str::FromStr for Fixed<$repr, FRAC> {
+      type Err = ParseFixedError;
+      fn from_str(s: &str) -> Result<Self, ParseFixedError> {
+        Self::parse_decimal(s)
+      }
+    }
+
+    // The radix formats print the raw bits.
+
+    impl<const FRAC: u32> std::fmt::LowerHex for Fixed<$repr, FRAC> {
+      fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::LowerHex::fmt(&self.0, f)
+      }
+    }
+
+    impl<const FRAC: u32> std::fmt::UpperHex for Fixed<$repr, FRAC> {
+      fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::UpperHex::fmt(&self.0, f)
+      }
+    }
+
+    impl<const FRAC: u32> std::fmt::Binary for Fixed<$repr, FRAC> {
+      fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Binary::fmt(&self.0, f)
+      }
+    }
+
+    // We serialize and deserialize numbers as strings to avoid precision issues with JSON.
+
+    impl<const FRAC: u32> ::serde::Serialize for Fixed<$repr, FRAC> {
+      fn serialize<S: ::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0.to_string())
+      }
+    }
+
+    impl<'de, const FRAC: u32> ::serde::Deserialize<'de> for Fixed<$repr, FRAC> {
+      fn deserialize<D: ::serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Ok(Self(s.parse::<$repr>().map_err(::serde::de::Error::custom)?))
+      }
+    }
+  };
 }
 
-impl std::ops::ShrAssign<u32> for Q64 {
-  fn shr_assign(&mut self, shift: u32) {
-    *self = *self >> shift;
+impl_fixed!(i32);
+impl_fixed!(i64);
+impl_fixed!(i128);
+
+impl Q64 {
+  pub fn angle_to_vec(self) -> Q64Vec {
+    let (sin, cos) = self.sin_cos();
+    Q64Vec { x: cos, y: sin }
   }
 }
 
-// We serialize and deserialize numbers as strings to avoid precision issues with JSON.
+// ==================== Fixed-point vector ====================
 
-impl serde::Serialize for Q64 {
-  fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
-    serializer.serialize_str(&self.0.to_string())
+/// The Euclidean norm of raw `Q64` components, rounded down, in the same units. Never overflows for
+/// up to four components.
+pub(crate) fn norm_bits(components: &[i64]) -> u128 {
+  use num_integer::Roots;
+  let sum_of_squares = components
+    .iter()
+    .try_fold(0u128, |sum, &c| sum.checked_add(c.unsigned_abs() as u128 * c.unsigned_abs() as u128));
+  match sum_of_squares {
+    Some(sum) => sum.sqrt(),
+    // Only possible with four components near the extremes; halving them loses just the low bit.
+    None => 2 * norm_bits(&components.iter().map(|c| c >> 1).collect::<Vec<_>>()),
   }
 }
 
-impl<'de> serde::Deserialize<'de> for Q64 {
-  fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
-    let s = String::deserialize(deserializer)?;
-    Ok(Self(s.parse::<i64>().map_err(serde::de::Error::custom)?))
+/// Scales raw `Q64` components to unit length, rounding each toward zero. Zero stays zero.
+pub(crate) fn normalized_bits<const N: usize>(components: [i64; N]) -> [i64; N] {
+  // Scale small inputs up first, since the direction doesn't depend on the scale and the norm is
+  // then computed with about 62 significant bits.
+  let largest = components.iter().map(|c| c.unsigned_abs()).max().unwrap_or(0);
+  if largest == 0 {
+    return [0; N];
   }
+  let components = components.map(|c| c << largest.leading_zeros().saturating_sub(1));
+  let norm = norm_bits(&components) as i128;
+  components.map(|c| (((c as i128) << Q64_SHIFT) / norm) as i64)
 }
 
-// ==================== Fixed-point vector ====================
+/// Parses `N` comma-separated raw integers, as written by the serde impls of the vector types.
+fn parse_raw_parts<const N: usize, E: ::serde::de::Error>(s: &str) -> Result<[i64; N], E> {
+  let mut parts = s.split(',');
+  let mut values = [0; N];
+  for value in values.iter_mut() {
+    *value = parts
+      .next()
+      .ok_or_else(|| E::custom("missing part"))?
+      .parse::<i64>()
+      .map_err(E::custom)?;
+  }
+  if parts.next().is_some() {
+    return Err(E::custom("too many parts"));
+  }
+  Ok(values)
+}
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Q64Vec {
@@ -208,13 +1108,24 @@ impl Q64Vec {
     y: Q64::ZERO,
   };
 
-  pub fn new(x: Q64, y: Q64) -> Self {
+  pub const fn new(x: Q64, y: Q64) -> Self {
     Self { x, y }
   }
 
-  // Based on https://stackoverflow.com/a/14100975/3047059
+  /// The angle of the vector in [0, PI_TIMES_2), accurate to within about half an ulp. The zero
+  /// vector has angle zero.
   pub fn get_angle(self) -> Q64 {
-    const B: Q64 = Q64(2560775466); // 0.596227
+    let angle = self.y.atan2(self.x);
+    match angle < Q64::ZERO {
+      true => angle + Q64::PI_TIMES_2,
+      false => angle,
+    }
+  }
+
+  /// The cheaper rational approximation of `get_angle`, accurate to roughly 5e-3.
+  // Based on https://stackoverflow.com/a/14100975/3047059
+  pub fn get_angle_fast(self) -> Q64 {
+    const B: Q64 = q64!("0.596227");
     let x_sign = self.x < Q64::ZERO;
     let y_sign = self.y < Q64::ZERO;
     let q = 4 * (!x_sign & y_sign) as i32 + 2 * x_sign as i32;
@@ -231,41 +1142,292 @@ impl Q64Vec {
     (Q64::from_i32(q) + atan_1q) * Q64::PI_OVER_TWO
   }
 
-  pub fn from_i32(x: i32, y: i32) -> Self {
-    Self {
-      x: Q64::from_i32(x),
-      y: Q64::from_i32(y),
-    }
+  pub const fn from_i32(x: i32, y: i32) -> Self {
+    Self {
+      x: Q64::from_i32(x),
+      y: Q64::from_i32(y),
+    }
+  }
+
+  pub const fn from_f64(x: f64, y: f64) -> Self {
+    Self {
+      x: Q64::from_f64(x),
+      y: Q64::from_f64(y),
+    }
+  }
+
+  pub fn from_f64_round(x: f64, y: f64) -> Self {
+    Self {
+      x: Q64::from_f64_round(x),
+      y: Q64::from_f64_round(y),
+    }
+  }
+
+  pub fn try_from_f64(x: f64, y: f64) -> Result<Self, FixedError> {
+    Ok(Self {
+      x: Q64::try_from_f64(x)?,
+      y: Q64::try_from_f64(y)?,
+    })
+  }
+
+  pub fn to_f64(self) -> (f64, f64) {
+    (self.x.to_f64(), self.y.to_f64())
+  }
+
+  /// The raw bits of x then y, little-endian.
+  pub fn to_le_bytes(self) -> [u8; 16] {
+    let mut bytes = [0; 16];
+    bytes[..8].copy_from_slice(&self.x.to_le_bytes());
+    bytes[8..].copy_from_slice(&self.y.to_le_bytes());
+    bytes
+  }
+
+  pub fn from_le_bytes(bytes: [u8; 16]) -> Self {
+    let (x, y) = bytes.split_at(8);
+    Self {
+      x: Q64::from_le_bytes(x.try_into().unwrap()),
+      y: Q64::from_le_bytes(y.try_into().unwrap()),
+    }
+  }
+
+  pub fn norm_squared(self) -> Q64 {
+    self.x * self.x + self.y * self.y
+  }
+
+  /// Rounds toward zero like `sqrt`. The squares are computed exactly in 128 bits, so this is
+  /// correct across the whole range, saturating only when the norm itself exceeds the largest `Q64`.
+  pub fn norm(self) -> Q64 {
+    Q64::from_bits(norm_bits(&[self.x.0, self.y.0]).min(i64::MAX as u128) as i64)
+  }
+
+  /// Each coordinate is rounded toward zero, for any input. The zero vector normalizes to zero.
+  pub fn normalized(self) -> Self {
+    let [x, y] = normalized_bits([self.x.0, self.y.0]);
+    Self::new(Q64::from_bits(x), Q64::from_bits(y))
+  }
+
+  pub fn dot(self, rhs: Self) -> Q64 {
+    self.x * rhs.x + self.y * rhs.y
+  }
+
+  /// The z component of the 3D cross product, also known as the perp-dot product. Positive when
+  /// `rhs` is counterclockwise from `self`.
+  pub fn cross(self, rhs: Self) -> Q64 {
+    self.x * rhs.y - self.y * rhs.x
+  }
+
+  /// The vector rotated a quarter turn counterclockwise.
+  pub fn perp(self) -> Self {
+    Self {
+      x: -self.y,
+      y: self.x,
+    }
+  }
+
+  /// Rotates counterclockwise by `angle` radians.
+  pub fn rotate(self, angle: Q64) -> Self {
+    let (sin, cos) = angle.sin_cos();
+    self.rotate_by(Self::new(cos, sin))
+  }
+
+  /// Rotates by the angle of `unit`, which should have norm one, as if multiplying complex numbers.
+  /// This is cheaper than `rotate` when the same rotation is applied many times.
+  pub fn rotate_by(self, unit: Self) -> Self {
+    Self {
+      x: self.x * unit.x - self.y * unit.y,
+      y: self.x * unit.y + self.y * unit.x,
+    }
+  }
+
+  /// The component of `self` along `onto`, with each coordinate rounded toward zero. The dot
+  /// product and squared norm are exact in 128 bits, so this is correct however large or small
+  /// `onto` is, wrapping only if the projection itself overflows. Projecting onto the zero vector
+  /// gives zero.
+  pub fn project_onto(self, onto: Self) -> Self {
+    // Products as a sign and a magnitude, since the dot product can reach 2^127.
+    let product = |a: Q64, b: Q64| {
+      let magnitude = a.0.unsigned_abs() as u128 * b.0.unsigned_abs() as u128;
+      ((a.0 < 0) != (b.0 < 0), magnitude)
+    };
+    let ((x_negative, x), (y_negative, y)) = (product(self.x, onto.x), product(self.y, onto.y));
+    let (dot_negative, dot) = match (x_negative == y_negative, x >= y) {
+      (true, _) => (x_negative, x + y),
+      (false, true) => (x_negative, x - y),
+      (false, false) => (y_negative, y - x),
+    };
+    let norm_squared = product(onto.x, onto.x).1 + product(onto.y, onto.y).1;
+    if norm_squared == 0 {
+      return Self::ZERO;
+    }
+    // Each coordinate is onto * dot / norm_squared, where the Q.64 units of the last two cancel.
+    let coordinate = |c: Q64| {
+      let (quotient, _, _) = repr::div_rem_u256(repr::widening_mul_u128(c.0.unsigned_abs() as u128, dot), norm_squared);
+      Q64::from_bits(match (c.0 < 0) != dot_negative {
+        true => (quotient as i64).wrapping_neg(),
+        false => quotient as i64,
+      })
+    };
+    Self::new(coordinate(onto.x), coordinate(onto.y))
+  }
+
+  /// Reflects off a surface with the given normal, which should have norm one.
+  pub fn reflect(self, normal: Self) -> Self {
+    self - normal * (Q64::from_i32(2) * self.dot(normal))
+  }
+
+  pub fn distance(self, other: Self) -> Q64 {
+    (other - self).norm()
+  }
+
+  pub fn distance_squared(self, other: Self) -> Q64 {
+    (other - self).norm_squared()
+  }
+
+  /// Interpolates each coordinate like `Q64::lerp`.
+  pub fn lerp(self, other: Self, t: Q64) -> Self {
+    Self {
+      x: self.x.lerp(other.x, t),
+      y: self.y.lerp(other.y, t),
+    }
+  }
+
+  /// Scales the vector down to `max_length` if it is longer, keeping its direction. A negative
+  /// `max_length` is treated as zero, so it gives the zero vector rather than reversing.
+  pub fn clamp_length(self, max_length: Q64) -> Self {
+    let max_length = max_length.max(Q64::ZERO);
+    match self.norm() > max_length {
+      true => self.normalized() * max_length,
+      false => self,
+    }
+  }
+
+  /// The unsigned angle between the two vectors in [0, PI_ROUNDED], accurate to within about half an
+  /// ulp for any inputs. It is zero if either vector is zero.
+  pub fn angle_between(self, other: Self) -> Q64 {
+    // Exact products in Q.64, halved so that the sums can't overflow.
+    let product = |a: Q64, b: Q64| (a.0 as i128 * b.0 as i128) >> 1;
+    let dot = product(self.x, other.x) + product(self.y, other.y);
+    let cross = product(self.x, other.y) - product(self.y, other.x);
+    Q64::from_bits(trig::from_q62(trig::atan2_q62(cross, dot), Q64_SHIFT) as i64).abs()
+  }
+
+  pub fn overflowing_add(self, rhs: Self) -> (Self, bool) {
+    let (x, x_overflow) = self.x.overflowing_add(rhs.x);
+    let (y, y_overflow) = self.y.overflowing_add(rhs.y);
+    (Self { x, y }, x_overflow || y_overflow)
+  }
+
+  pub fn overflowing_sub(self, rhs: Self) -> (Self, bool) {
+    let (x, x_overflow) = self.x.overflowing_sub(rhs.x);
+    let (y, y_overflow) = self.y.overflowing_sub(rhs.y);
+    (Self { x, y }, x_overflow || y_overflow)
+  }
+
+  pub fn overflowing_mul(self, rhs: Q64) -> (Self, bool) {
+    let (x, x_overflow) = self.x.overflowing_mul(rhs);
+    let (y, y_overflow) = self.y.overflowing_mul(rhs);
+    (Self { x, y }, x_overflow || y_overflow)
+  }
+
+  pub fn overflowing_div(self, rhs: Q64) -> (Self, bool) {
+    let (x, x_overflow) = self.x.overflowing_div(rhs);
+    let (y, y_overflow) = self.y.overflowing_div(rhs);
+    (Self { x, y }, x_overflow || y_overflow)
+  }
+
+  pub fn checked_add(self, rhs: Self) -> Option<Self> {
+    Some(Self {
+      x: self.x.checked_add(rhs.x)?,
+      y: self.y.checked_add(rhs.y)?,
+    })
+  }
+
+  pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+    Some(Self {
+      x: self.x.checked_sub(rhs.x)?,
+      y: self.y.checked_sub(rhs.y)?,
+    })
+  }
+
+  pub fn checked_mul(self, rhs: Q64) -> Option<Self> {
+    Some(Self {
+      x: self.x.checked_mul(rhs)?,
+      y: self.y.checked_mul(rhs)?,
+    })
+  }
+
+  pub fn checked_div(self, rhs: Q64) -> Option<Self> {
+    Some(Self {
+      x: self.x.checked_div(rhs)?,
+      y: self.y.checked_div(rhs)?,
+    })
+  }
+
+  pub fn try_div(self, rhs: Q64) -> Result<Self, FixedError> {
+    Ok(Self {
+      x: self.x.try_div(rhs)?,
+      y: self.y.try_div(rhs)?,
+    })
+  }
+
+  pub fn checked_neg(self) -> Option<Self> {
+    Some(Self {
+      x: self.x.checked_neg()?,
+      y: self.y.checked_neg()?,
+    })
+  }
+
+  pub fn wrapping_add(self, rhs: Self) -> Self {
+    self.overflowing_add(rhs).0
+  }
+
+  pub fn wrapping_sub(self, rhs: Self) -> Self {
+    self.overflowing_sub(rhs).0
   }
 
-  pub fn from_f64(x: f64, y: f64) -> Self {
+  pub fn wrapping_mul(self, rhs: Q64) -> Self {
+    self.overflowing_mul(rhs).0
+  }
+
+  pub fn wrapping_div(self, rhs: Q64) -> Self {
+    self.overflowing_div(rhs).0
+  }
+
+  pub fn saturating_add(self, rhs: Self) -> Self {
     Self {
-      x: Q64::from_f64(x),
-      y: Q64::from_f64(y),
+      x: self.x.saturating_add(rhs.x),
+      y: self.y.saturating_add(rhs.y),
     }
   }
 
-  pub fn to_f64(self) -> (f64, f64) {
-    (self.x.to_f64(), self.y.to_f64())
+  pub fn saturating_sub(self, rhs: Self) -> Self {
+    Self {
+      x: self.x.saturating_sub(rhs.x),
+      y: self.y.saturating_sub(rhs.y),
+    }
   }
 
-  pub fn norm_squared(self) -> Q64 {
-    self.x * self.x + self.y * self.y
+  pub fn saturating_mul(self, rhs: Q64) -> Self {
+    Self {
+      x: self.x.saturating_mul(rhs),
+      y: self.y.saturating_mul(rhs),
+    }
   }
 
-  pub fn norm(self) -> Q64 {
-    // Avoid overflow issues by simply returning a large value if any coordinate is large enough.
-    if self.x.abs() > Q64::from_i32(30_000) || self.y.abs() > Q64::from_i32(30_000) {
-      return Q64::from_i32(1_000_000);
+  pub fn saturating_div(self, rhs: Q64) -> Self {
+    Self {
+      x: self.x.saturating_div(rhs),
+      y: self.y.saturating_div(rhs),
     }
-    (self.x * self.x + self.y * self.y).sqrt()
   }
+}
 
-  pub fn normalized(self) -> Self {
-    let norm = self.norm();
-    match norm {
-      Q64::ZERO => Self::ZERO,
-      _ => self / norm,
+/// Prints `(x, y)`, passing any precision on to both coordinates.
+impl std::fmt::Display for Q64Vec {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match f.precision() {
+      Some(precision) => write!(f, "({:.*}, {:.*})", precision, self.x, precision, self.y),
+      None => write!(f, "({}, {})", self.x, self.y),
     }
   }
 }
@@ -327,6 +1489,18 @@ impl std::ops::Div<Q64> for Q64Vec {
   }
 }
 
+impl std::iter::Sum for Q64Vec {
+  fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+    iter.fold(Self::ZERO, |a, b| a + b)
+  }
+}
+
+impl<'a> std::iter::Sum<&'a Self> for Q64Vec {
+  fn sum<I: Iterator<Item = &'a Self>>(iter: I) -> Self {
+    iter.copied().sum()
+  }
+}
+
 impl std::ops::AddAssign for Q64Vec {
   fn add_assign(&mut self, rhs: Self) {
     *self = *self + rhs;
@@ -351,28 +1525,16 @@ impl std::ops::DivAssign<Q64> for Q64Vec {
   }
 }
 
-impl serde::Serialize for Q64Vec {
-  fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+impl ::serde::Serialize for Q64Vec {
+  fn serialize<S: ::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
     serializer.serialize_str(&format!("{},{}", self.x.0, self.y.0))
   }
 }
 
-impl<'de> serde::Deserialize<'de> for Q64Vec {
-  fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
-    let s = String::deserialize(deserializer)?;
-    let mut parts = s.split(',');
-    let mut get_val = || Ok(
-      parts.next()
-        .ok_or_else(|| serde::de::Error::custom("missing part"))?
-        .parse::<i64>()
-        .map_err(serde::de::Error::custom)?
-    );
-    let x = get_val()?;
-    let y = get_val()?;
-    if parts.next().is_some() {
-      return Err(serde::de::Error::custom("too many parts"));
-    }
-    Ok(Self::new(Q64(x), Q64(y)))
+impl<'de> ::serde::Deserialize<'de> for Q64Vec {
+  fn deserialize<D: ::serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    let [x, y] = parse_raw_parts(&String::deserialize(deserializer)?)?;
+    Ok(Self::new(Q64::from_bits(x), Q64::from_bits(y)))
   }
 }
 
@@ -405,6 +1567,379 @@ mod tests {
     }
   }
 
+  #[test]
+  fn test_sin_cos_precision_tiers() {
+    let ulp = Q64::EPSILON.to_f64();
+    let mut max_error: f64 = 0.0;
+    for i in -20000..20000 {
+      let x = Q64::from_bits(i * 1_234_567_891 + 17);
+      let (actual_sin, actual_cos) = x.to_f64().sin_cos();
+      max_error = max_error.max((actual_sin - x.sin().to_f64()).abs());
+      max_error = max_error.max((actual_cos - x.cos().to_f64()).abs());
+      assert!((actual_sin - x.sin_fast().to_f64()).abs() < 1e-3);
+      assert!((actual_cos - x.cos_fast().to_f64()).abs() < 1e-3);
+    }
+    for i in 0..1000 {
+      let x = Q64::from_i32(i * 2_000_000);
+      max_error = max_error.max((x.to_f64().sin() - x.sin().to_f64()).abs());
+    }
+    assert!(max_error <= 0.51 * ulp);
+    let x = Fixed::<i128, 62>::from_f64(1.25);
+    assert!((x.sin().to_f64() - 1.25f64.sin()).abs() < 1e-15);
+    assert!((Q32::from_f64(-2.0).cos().to_f64() - 2.0f64.cos()).abs() <= 0.51 / 65536.0);
+  }
+
+  #[test]
+  fn test_sin_fast_matches_original_sin() {
+    // The original `Q64::sin`, on raw bits.
+    fn original_sin(x: i64) -> i64 {
+      let mul = |a: i64, b: i64| ((a as i128 * b as i128) >> 32) as i64;
+      let mut x = x.rem_euclid(26986075409);
+      let mut negate = false;
+      if x > 13493037704 {
+        x -= 13493037704;
+        negate = true;
+      }
+      if x > 6746518852 {
+        x = 13493037704 - x;
+      }
+      let x2 = mul(x, x);
+      let x3 = mul(x2, x);
+      let x5 = mul(x3, x2);
+      let x7 = mul(x5, x2);
+      let y = x - mul(x3, 715827883) + mul(x5, 35791394) - mul(x7, 852176);
+      match negate {
+        true => -y,
+        false => y,
+      }
+    }
+    assert_eq!(Q64::PI_OVER_TWO.sin_fast(), Q64::from_bits(4294293422));
+    for i in -50000..50000 {
+      let x = Q64::from_bits(i * 1_234_567 + i % 7);
+      assert_eq!(x.sin_fast().0, original_sin(x.0));
+      assert_eq!(x.cos_fast().0, original_sin(Q64::PI_OVER_TWO.0 - x.0));
+    }
+    // Layouts too narrow for the term-by-term sum still stay within the series' accuracy.
+    let narrow = Fixed::<i32, 27>::from_f64(1.5);
+    assert!((narrow.sin_fast().to_f64() - 1.5f64.sin()).abs() < 1e-3);
+  }
+
+  #[test]
+  fn test_sin_cos_matches_separate_calls() {
+    for i in -5000..5000 {
+      let x = Q64::from_bits(i * 987_654_321 - 3);
+      assert_eq!(x.sin_cos(), (x.sin(), x.cos()));
+      assert_eq!(x.angle_to_vec(), Q64Vec::new(x.cos(), x.sin()));
+      let y = Q32::from_bits(i as i32 * 4099);
+      assert_eq!(y.sin_cos(), (y.sin(), y.cos()));
+    }
+  }
+
+  #[test]
+  fn test_inverse_trig() {
+    let ulp = Q64::EPSILON.to_f64();
+    for x in -60..60 {
+      for y in -60..60 {
+        let (qx, qy) = (Q64::from_f64(x as f64 * 0.37), Q64::from_f64(y as f64 * 1.13));
+        let actual = qy.to_f64().atan2(qx.to_f64());
+        assert!((qy.atan2(qx).to_f64() - actual).abs() <= 0.51 * ulp);
+      }
+    }
+    // The accurate angles round to nearest, like the rounded constants.
+    assert_eq!(Q64::ZERO.atan2(-Q64::ONE), Q64::PI_ROUNDED);
+    assert_eq!(Q64::ONE.atan2(Q64::ZERO), Q64::FRAC_PI_2);
+    assert_eq!(Q64::ONE.atan(), Q64::FRAC_PI_4);
+    assert_eq!((-Q64::ONE).acos(), Q64::PI_ROUNDED);
+    assert_eq!((-Q64::ONE).asin(), -Q64::FRAC_PI_2);
+    assert_eq!(Q32::ZERO.atan2(-Q32::ONE), Q32::PI_ROUNDED);
+    assert_eq!(Q64::ZERO.atan2(Q64::ZERO), Q64::ZERO);
+    for i in -1000..=1000 {
+      let x = Q64::from_f64(i as f64 / 1000.0);
+      assert!((x.asin().to_f64() - x.to_f64().asin()).abs() <= 0.51 * ulp);
+      assert!((x.acos().to_f64() - x.to_f64().acos()).abs() <= 0.51 * ulp);
+      let t = Q64::from_f64(i as f64 * 0.071);
+      assert!((t.atan().to_f64() - t.to_f64().atan()).abs() <= 0.51 * ulp);
+    }
+    let big = Fixed::<i128, 16>(i128::MAX);
+    assert_eq!(big.atan2(Fixed(i128::MIN)), Fixed::<i128, 16>::from_bits(154416));
+    assert!((Q32::from_f64(0.5).asin().to_f64() - 0.5f64.asin()).abs() <= 0.51 / 65536.0);
+  }
+
+  #[test]
+  fn test_get_angle_fast() {
+    for x in -50..50 {
+      for y in -50..50 {
+        let v = Q64Vec::from_i32(x, y);
+        let mut actual_atan2 = v.y.to_f64().atan2(v.x.to_f64());
+        if actual_atan2 < 0.0 {
+          actual_atan2 += 2.0 * std::f64::consts::PI;
+        }
+        assert!((actual_atan2 - v.get_angle_fast().to_f64()).abs() < 5e-3);
+        assert!((actual_atan2 - v.get_angle().to_f64()).abs() < 1e-9);
+        assert!(v.get_angle() < Q64::PI_TIMES_2);
+      }
+    }
+  }
+
+  #[test]
+  fn test_exp_log() {
+    let ulp = Q64::EPSILON.to_f64();
+    for i in -2000..2000 {
+      let x = Q64::from_f64(i as f64 * 0.0107);
+      let exp = x.to_f64().exp();
+      assert!((x.exp().unwrap().to_f64() - exp).abs() <= 0.51 * ulp + exp * 1e-15);
+      let exp2 = x.to_f64().exp2();
+      assert!((x.exp2().unwrap().to_f64() - exp2).abs() <= 0.51 * ulp + exp2 * 1e-15);
+    }
+    for i in 1..4000 {
+      let x = Q64::from_bits(i * i * i * 7919 + i);
+      assert!((x.ln().unwrap().to_f64() - x.to_f64().ln()).abs() <= 0.51 * ulp);
+      assert!((x.log2().unwrap().to_f64() - x.to_f64().log2()).abs() <= 0.51 * ulp);
+      let y = Q64::from_f64(i as f64 * 1e-3 - 2.0);
+      let pow = x.to_f64().powf(y.to_f64());
+      if let Some(computed) = x.powf(y) {
+        assert!((computed.to_f64() - pow).abs() <= 1.01 * ulp + pow * 1e-9);
+      } else {
+        assert!(pow > i32::MAX as f64);
+      }
+    }
+    assert_eq!(Q64::ZERO.ln(), None);
+    assert_eq!((-Q64::ONE).log2(), None);
+    assert_eq!(Q64::from_i32(8).log2(), Some(Q64::from_i32(3)));
+    assert_eq!(Q64::from_i32(-3).exp2(), Some(Q64::ONE >> 3));
+    assert_eq!(Q64::ZERO.exp(), Some(Q64::ONE));
+    assert_eq!(Q64::from_i32(22).exp(), None);
+    assert_eq!(Q64::from_i32(31).exp2(), None);
+    assert_eq!(Q64::from_i32(-23).exp(), Some(Q64::ZERO));
+    assert_eq!(Q64::from_i32(-1_000_000_000).exp(), Some(Q64::ZERO));
+    assert_eq!(Q64::from_i32(-3).powi(3), Some(Q64::from_i32(-27)));
+    assert_eq!(Q64::from_i32(2).powi(-2), Some(Q64::ONE >> 2));
+    assert_eq!(Q64::from_i32(2).powi(31), None);
+    assert_eq!(Q64::from_i32(1000).powi(-5), Some(Q64::ZERO));
+    // Reciprocals of powers too large to represent can still be representable.
+    let two = Q64::from_i32(2);
+    assert_eq!(two.powi(-31), Some(Q64::from_bits(2)));
+    assert_eq!(two.powi(-32), Some(Q64::EPSILON));
+    assert_eq!(two.powi(-33), Some(Q64::ZERO));
+    assert_eq!((-two).powi(-31), Some(Q64::from_bits(-2)));
+    assert_eq!(Q64::from_i32(3).powi(-20), Some(Q64::EPSILON));
+    assert_eq!(Q64::from_i32(3).powi(-13), Some(Q64::from_bits(2693)));
+    assert_eq!(Q64::HALF.powi(-30), Some(Q64::from_i32(1 << 30)));
+    assert_eq!(Q64::HALF.powi(-31), None);
+    assert_eq!((-Q64::HALF).powi(-31), Some(Q64::from_bits(i64::MIN)));
+    assert_eq!(Q64::EPSILON.powi(-1), None);
+    assert_eq!(Q64::EPSILON.powi(i32::MIN), None);
+    assert_eq!(Q64::ONE.powi(i32::MIN), Some(Q64::ONE));
+    assert_eq!(Q32::from_i32(2).powi(-16), Some(Q32::EPSILON));
+    assert_eq!(Fixed::<i128, 64>::from_i32(2).powi(-64), Some(Fixed::<i128, 64>::EPSILON));
+    // Small powers are no longer rounded before taking the reciprocal.
+    let x = Q64::from_f64(0.9);
+    let expected = x.to_f64().powi(-200);
+    assert!((x.powi(-200).unwrap().to_f64() - expected).abs() < expected * 1e-12);
+    for i in 0..1000i64 {
+      let x = Q64::from_bits((i * 68_718_952_447) % (1 << 36) + (1 << 31));
+      let n = -(i % 40) as i32 - 1;
+      let expected = x.to_f64().powi(n) * 4294967296.0;
+      if let Some(y) = x.powi(n) {
+        assert!((y.0 as f64 - expected).abs() <= 1.0 + expected * 1e-12, "{:?}^{}", x, n);
+      }
+    }
+    assert_eq!(Q64::ZERO.powi(-1), None);
+    assert_eq!(Q64::from_i32(9).powf(Q64::HALF), Some(Q64::from_i32(3)));
+    assert_eq!(Q64::ZERO.powf(Q64::HALF), Some(Q64::ZERO));
+    assert_eq!(Q64::ZERO.powf(-Q64::HALF), None);
+    assert_eq!((-Q64::ONE).powf(Q64::ONE), None);
+    assert_eq!(Q64::ONE.powf(Q64::from_i32(i32::MAX)), Some(Q64::ONE));
+    assert!((Q32::from_i32(3).exp().unwrap().to_f64() - 3f64.exp()).abs() <= 0.51 / 65536.0);
+    assert!((Fixed::<i128, 64>::from_i32(10).ln().unwrap().to_f64() - 10f64.ln()).abs() < 1e-15);
+    assert_eq!(Fixed::<i128, 100>::from_i32(19).exp(), None);
+  }
+
+  #[test]
+  fn test_ceiling_div_by_positive() {
+    // Exact quotients stay exact, and inexact ones round up, whatever the sign of `self`.
+    assert_eq!(Q64::from_i32(-2).ceiling_div_by_positive(Q64::ONE), Q64::from_i32(-2));
+    assert_eq!(Q64::from_i32(-6).ceiling_div_by_positive(Q64::from_i32(3)), Q64::from_i32(-2));
+    assert_eq!(Q64::from_i32(-3).ceiling_div_by_positive(Q64::from_i32(2)), Q64::from_bits(-3 << 31));
+    assert_eq!((-Q64::ONE).ceiling_div_by_positive(Q64::from_i32(3)), Q64::from_bits(-1431655765));
+    assert_eq!(Q64::ONE.ceiling_div_by_positive(Q64::from_i32(3)), Q64::from_bits(1431655766));
+    assert_eq!((-Q64::EPSILON).ceiling_div_by_positive(Q64::from_i32(1 << 20)), Q64::ZERO);
+    assert_eq!(Q32::from_i32(-7).ceiling_div_by_positive(Q32::from_i32(2)), Q32::from_bits(-7 << 15));
+    let mut rng = Q64Rng::new(1);
+    for _ in 0..1000 {
+      let (a, b) = (Q64::from_bits(rng.next_u64() as i64 >> 16), Q64::from_bits((rng.next_u64() >> 24) as i64 + 1));
+      // The least value whose product with b reaches a.
+      let ceiling = a.ceiling_div_by_positive(b).0;
+      assert!(((ceiling as i128) * b.0 as i128) >= (a.0 as i128) << 32);
+      assert!((((ceiling - 1) as i128) * b.0 as i128) < (a.0 as i128) << 32);
+    }
+  }
+
+  #[test]
+  fn test_fallible_twins() {
+    assert_eq!(Q64::from_i32(4).try_sqrt(), Ok(Q64::from_i32(2)));
+    assert_eq!((-Q64::EPSILON).try_sqrt(), Err(FixedError::NegativeSqrt));
+    assert_eq!(Q64::ONE.try_div(Q64::ZERO), Err(FixedError::DivideByZero));
+    assert_eq!(Q64::from_i32(1 << 20).try_div(Q64::EPSILON), Err(FixedError::Overflow));
+    assert_eq!(Q64::ONE.try_div(-Q64::HALF), Ok(Q64::from_i32(-2)));
+    assert_eq!(Q64::ONE.try_ceiling_div_by_positive(Q64::ZERO), Err(FixedError::NonPositiveDivisor));
+    assert_eq!(Q64::ONE.try_ceiling_div_by_positive(-Q64::ONE), Err(FixedError::NonPositiveDivisor));
+    assert_eq!(Q64::ONE.try_ceiling_div_by_positive(Q64::EPSILON), Err(FixedError::Overflow));
+    assert_eq!(Q64::ONE.try_ceiling_div_by_positive(Q64::from_i32(3)), Ok(Q64::from_bits(1431655766)));
+    assert_eq!((Q64::ONE + Q64::EPSILON).try_asin(), Err(FixedError::OutOfDomain));
+    assert_eq!((-Q64::ONE - Q64::EPSILON).try_acos(), Err(FixedError::OutOfDomain));
+    assert_eq!(Q64::ONE.try_acos(), Ok(Q64::ZERO));
+    assert_eq!(Q64::from_bits(i64::MIN).checked_neg(), None);
+    assert_eq!(Q64::from_bits(i64::MIN).checked_abs(), None);
+    assert_eq!(Q64::ONE.checked_shl(64), None);
+    assert_eq!(Q64::ONE.checked_shr(1), Some(Q64::HALF));
+    assert_eq!(Q64Vec::from_i32(1, 2).try_div(Q64::ZERO), Err(FixedError::DivideByZero));
+    assert_eq!(Q64Vec::from_i32(2, 4).try_div(Q64::from_i32(2)), Ok(Q64Vec::from_i32(1, 2)));
+    assert_eq!(FixedError::NegativeSqrt.to_string(), "sqrt of negative number");
+  }
+
+  #[test]
+  #[should_panic(expected = "sqrt of negative number")]
+  fn test_sqrt_still_panics() {
+    (-Q64::ONE).sqrt();
+  }
+
+  #[test]
+  fn test_conversions() {
+    let cases = [(2.5, 2, 3, 2, 3), (-2.5, -3, -2, -2, -3), (2.25, 2, 3, 2, 2), (-2.75, -3, -2, -2, -3), (7.0, 7, 7, 7, 7)];
+    for (x, floor, ceil, trunc, round) in cases {
+      let q = Q64::from_f64(x);
+      assert_eq!((q.to_i32_floor(), q.to_i32_ceil(), q.to_i32_trunc(), q.to_i32_round()), (floor, ceil, trunc, round));
+      assert_eq!(q.to_i32_round(), x.round() as i32);
+      assert_eq!(q.to_f32(), x as f32);
+    }
+    assert_eq!((-Q64::EPSILON).to_i32_trunc(), 0);
+    assert_eq!((-Q64::EPSILON).to_i32_round(), 0);
+
+    let ulp = Q64::EPSILON.to_f64();
+    assert_eq!(Q64::from_f64(-0.7 * ulp), Q64::ZERO);
+    assert_eq!(Q64::from_f64_round(-0.7 * ulp), -Q64::EPSILON);
+    assert_eq!(Q64::from_f64_round(2.5 * ulp), Q64::from_bits(2));
+    assert_eq!(Q64::from_f64_round(3.5 * ulp), Q64::from_bits(4));
+    assert_eq!(Q64::from_f64_round(f64::NAN), Q64::ZERO);
+    assert_eq!(Q64::try_from_f64(f64::NAN), Err(FixedError::NotFinite));
+    assert_eq!(Q64::try_from_f64(f64::NEG_INFINITY), Err(FixedError::NotFinite));
+    assert_eq!(Q64::try_from_f64(2147483648.0), Err(FixedError::Overflow));
+    assert_eq!(Q64::try_from_f64(-2147483648.0), Ok(Q64::from_i32(i32::MIN)));
+    assert_eq!(Q64::try_from_f64(-2147483649.0), Err(FixedError::Overflow));
+    assert_eq!(Q64::try_from_f64(-12.375), Ok(Q64::from_i32(-12) - Q64::from_f64(0.375)));
+    assert_eq!(Q32::try_from_f32(0.1), Ok(Q32::from_bits(6554)));
+    assert_eq!(Q32::from_f32(0.1), Q32::from_bits(6553));
+    assert_eq!(Q32::from_f32_round(-0.1), Q32::from_bits(-6554));
+    assert_eq!(Q64Vec::try_from_f64(1.0, f64::INFINITY), Err(FixedError::NotFinite));
+    assert_eq!(Q64Vec::from_f64_round(0.5, -ulp * 0.6), Q64Vec::new(Q64::HALF, -Q64::EPSILON));
+
+    assert_eq!(Q64::from(-7i16), Q64::from_i32(-7));
+    assert_eq!(Q32::from(i16::MIN), Q32::from_i32(i16::MIN as i32));
+    assert_eq!(Q64::try_from(-5i64), Ok(Q64::from_i32(-5)));
+    assert_eq!(Q64::try_from(1i64 << 31), Err(FixedError::Overflow));
+    assert_eq!(Q64::try_from(-(1i64 << 31)), Ok(Q64::from_i32(i32::MIN)));
+    assert_eq!(Fixed::<i64, 16>::try_from(1i64 << 40).map(|x| x.0), Ok(1 << 56));
+    assert_eq!(Fixed::<i128, 64>::try_from(i64::MIN).map(|x| x.0), Ok((i64::MIN as i128) << 64));
+  }
+
+  #[test]
+  fn test_rounding_family() {
+    for i in -1000..1000 {
+      let x = Q64::from_bits(i * 536_870_912 + (i % 7) * 13);
+      let f = x.to_f64();
+      assert_eq!(x.floor().to_f64(), f.floor());
+      assert_eq!(x.ceil().to_f64(), f.ceil());
+      assert_eq!(x.trunc().to_f64(), f.trunc());
+      assert_eq!(x.round().to_f64(), f.round());
+      assert_eq!(x.round_ties_even().to_f64(), f.round_ties_even());
+      assert_eq!(x.fract().to_f64(), f.fract());
+      assert_eq!(x.signum().to_f64(), if f == 0.0 { 0.0 } else { f.signum() });
+      assert_eq!(x.is_negative(), f < 0.0);
+      assert_eq!(x.is_positive(), f > 0.0);
+      let m = Q64::from_f64(2.75);
+      assert_eq!(x.rem_euclid(m).to_f64(), f.rem_euclid(2.75));
+      assert_eq!(x.rem_euclid(-m).to_f64(), f.rem_euclid(-2.75));
+      assert_eq!(x.clamp(-m, m).to_f64(), f.clamp(-2.75, 2.75));
+      assert_eq!(x.min(m).to_f64(), f.min(2.75));
+      assert_eq!(x.max(m).to_f64(), f.max(2.75));
+    }
+    let (a, b) = (Q64::from_i32(-3), Q64::from_f64(4.5));
+    assert_eq!(a.lerp(b, Q64::ZERO), a);
+    assert_eq!(a.lerp(b, Q64::ONE), b);
+    assert_eq!(a.lerp(b, Q64::HALF), Q64::from_f64(0.75));
+    assert_eq!(Q32::from_f64(-1.5).round(), Q32::from_i32(-2));
+    assert_eq!(Q32::from_f64(-1.5).round_ties_even(), Q32::from_i32(-2));
+    assert_eq!(Q32::from_f64(-0.5).round_ties_even(), Q32::ZERO);
+    assert_eq!(Q64::from_bits(i64::MIN).trunc(), Q64::from_bits(i64::MIN));
+    assert_eq!(Q64::from_bits(i64::MIN + 1).round(), Q64::from_bits(i64::MIN));
+  }
+
+  #[test]
+  fn test_display_and_parse() {
+    assert_eq!(Q64::PI_OVER_TWO.to_string(), "1.5707963267");
+    assert_eq!(format!("{:.4}", Q64::PI_OVER_TWO), "1.5708");
+    assert_eq!(format!("{:.0}", Q64::from_f64(2.5)), "2");
+    assert_eq!(format!("{:.0}", Q64::from_f64(3.5)), "4");
+    assert_eq!(format!("{:.2}", Q64::from_f64(-0.001)), "-0.00");
+    assert_eq!(format!("{:.3}", Q64::from_f64(9.9999)), "10.000");
+    assert_eq!(format!("{:>8.2}|{:+}|{:08.3}", Q64::ONE, Q64::HALF, -Q64::HALF), "    1.00|+0.5|-000.500");
+    assert_eq!(Q64::from_i32(-12).to_string(), "-12");
+    assert_eq!(Q64::from_bits(i64::MIN).to_string(), "-2147483648");
+    assert_eq!(Q64::EPSILON.to_string(), "0.0000000002");
+    assert_eq!(format!("{:.40}", Q64::EPSILON), "0.0000000002328306436538696289062500000000");
+    assert_eq!(Q32::from_f64_round(0.1).to_string(), "0.1");
+    assert_eq!(format!("{:x} {:X} {:b}", Q32::from_bits(255), Q32::from_bits(255), Q32::ONE), "ff FF 10000000000000000");
+    assert_eq!(format!("{:.1}", Q64Vec::from_f64(1.25, -2.0)), "(1.2, -2.0)");
+
+    assert_eq!("-12.375".parse::<Q64>(), Ok(Q64::from_f64(-12.375)));
+    assert_eq!("1e-3".parse::<Q64>(), Ok(Q64::from_f64_round(1e-3)));
+    assert_eq!("+0.5E1".parse::<Q64>(), Ok(Q64::from_i32(5)));
+    assert_eq!(".25".parse::<Q64>(), Ok(Q64::ONE >> 2));
+    assert_eq!("7.".parse::<Q64>(), Ok(Q64::from_i32(7)));
+    assert_eq!("0e999999999999999999999".parse::<Q64>(), Ok(Q64::ZERO));
+    assert_eq!("1e-999999999999999999999".parse::<Q64>(), Ok(Q64::ZERO));
+    assert_eq!("-2147483648".parse::<Q64>(), Ok(Q64::from_i32(i32::MIN)));
+    assert_eq!("2147483648".parse::<Q64>(), Err(ParseFixedError::Overflow));
+    assert_eq!("1e40".parse::<Q64>(), Err(ParseFixedError::Overflow));
+    // Exactly half an ulp rounds to even, and anything beyond it rounds up.
+    assert_eq!("0.00000000011641532182693481445312500".parse::<Q64>(), Ok(Q64::ZERO));
+    assert_eq!("0.00000000011641532182693481445312501".parse::<Q64>(), Ok(Q64::EPSILON));
+    assert_eq!("0.00000000034924596548080444335937500".parse::<Q64>(), Ok(Q64::from_bits(2)));
+    for bad in ["", "-", ".", "e5", "1e", "1.2.3", "--1", "+-1", " 1", "1 ", "0x10", "inf", "NaN", "1e+-2"] {
+      assert_eq!(bad.parse::<Q64>(), Err(ParseFixedError::Invalid), "{:?}", bad);
+    }
+
+    // Shortest output always parses back to the same value.
+    for i in -3000..3000 {
+      let x = Q64::from_bits(i * 1_000_000_007 + i * i * 31);
+      assert_eq!(x.to_string().parse::<Q64>(), Ok(x));
+      let y = Q32::from_bits(i as i32 * 7919);
+      assert_eq!(y.to_string().parse::<Q32>(), Ok(y));
+      let z = Fixed::<i128, 100>::from_bits((i as i128) << 90 | 12345);
+      assert_eq!(z.to_string().parse::<Fixed<i128, 100>>(), Ok(z));
+      let w = Fixed::<i128, 127>::from_bits((i as i128) << 114 | 987654321);
+      assert_eq!(w.to_string().parse::<Fixed<i128, 127>>(), Ok(w));
+    }
+    // With 125 or more fractional bits, ten times the fraction doesn't fit in a u128.
+    type Q1_127 = Fixed<i128, 127>;
+    assert_eq!(Q1_127::from_bits(1 << 126).to_string(), "0.5");
+    assert_eq!(Q1_127::from_bits(i128::MIN).to_string(), "-1");
+    assert_eq!(format!("{:.3}", Q1_127::from_bits(i128::MAX)), "1.000");
+    assert_eq!(format!("{:.2}", Q1_127::from_bits(5 << 122)), "0.16");
+    assert_eq!(format!("{:.2}", Fixed::<i128, 125>::from_bits(3 << 123)), "0.75");
+
+    // Parsing time is linear in the length of the literal, since digits past the first 170 are
+    // only checked for being nonzero.
+    let long = format!("1.{}", "3".repeat(1_000_000));
+    assert_eq!(long.parse::<Q64>(), "1.333333333333333333333".parse());
+    let long = format!("{}e-999991", "7".repeat(1_000_000));
+    assert_eq!(long.parse::<Q64>(), "777777777.77777777777777".parse());
+    // The dropped digits still break ties.
+    let above_tie = format!("0.000000000116415321826934814453125{}1", "0".repeat(200));
+    assert_eq!(above_tie.parse::<Q64>(), Ok(Q64::EPSILON));
+  }
+
   #[test]
   fn test_angle_to_vec() {
     for x in -50..50 {
@@ -420,6 +1955,85 @@ mod tests {
     }
   }
 
+  #[test]
+  fn test_other_layouts() {
+    macro_rules! check_layout {
+      ($t:ty, $tolerance:expr) => {
+        for i in -100..100 {
+          let a = <$t>::from_f64(i as f64 * 1.37);
+          let b = <$t>::from_f64(i as f64 * -0.61 + 0.3);
+          assert!(((a * b).to_f64() - a.to_f64() * b.to_f64()).abs() < $tolerance);
+          assert!(((a / b).to_f64() - a.to_f64() / b.to_f64()).abs() < $tolerance);
+          assert!((a.abs().sqrt().to_f64() - a.to_f64().abs().sqrt()).abs() < $tolerance);
+          assert!((a.sin().to_f64() - a.to_f64().sin()).abs() < 1e-3);
+          assert!((a.cos().to_f64() - a.to_f64().cos()).abs() < 1e-3);
+          let s = serde_json::to_string(&a).unwrap();
+          assert_eq!(a, serde_json::from_str::<$t>(&s).unwrap());
+        }
+      };
+    }
+    check_layout!(Q32, 1e-3);
+    check_layout!(Fixed<i64, 16>, 1e-3);
+    check_layout!(Fixed<i128, 64>, 1e-9);
+    assert_eq!(Fixed::<i64, 16>::from_i32(1 << 30).to_i32(), 1 << 30);
+  }
+
+  #[test]
+  fn test_wide_arithmetic_agrees() {
+    // The i128 path does its own 256-bit arithmetic, so check it against the i64 path.
+    let values = [0, 1, -1, 3, -7, 1 << 31, -(1 << 33) + 5, 123456789012, -98765432109, i64::MAX >> 20];
+    let agree = |narrow: (i64, bool), wide: (i128, bool)| {
+      assert!(!wide.1);
+      assert_eq!(narrow, (wide.0 as i64, wide.0 != wide.0 as i64 as i128));
+    };
+    for &a in &values {
+      for &b in &values {
+        agree(a.overflowing_mul_shr(b, 32), (a as i128).overflowing_mul_shr(b as i128, 32));
+        if b != 0 {
+          agree(a.overflowing_shl_div(32, b), (a as i128).overflowing_shl_div(32, b as i128));
+          agree(a.overflowing_shl_div_ceil(32, b), (a as i128).overflowing_shl_div_ceil(32, b as i128));
+        }
+      }
+    }
+    assert_eq!(i128::MIN.overflowing_mul_shr(-1, 0), (i128::MIN, true));
+    assert_eq!(i128::MAX.overflowing_mul_shr(i128::MAX, 126), (-4, true));
+    assert_eq!((1i128 << 100).overflowing_mul_shr(1 << 100, 80), (1 << 120, false));
+    assert_eq!((-1i128).overflowing_shl_div(127, 1), (i128::MIN, false));
+    assert_eq!(1i128.overflowing_shl_div(127, 1), (i128::MIN, true));
+    assert_eq!((-5i128).overflowing_shl_div_ceil(0, 2), (-2, false));
+    assert_eq!((-4i128).overflowing_shl_div_ceil(0, 2), (-2, false));
+  }
+
+  #[test]
+  fn test_overflow_families() {
+    let big = Q64::from_i32(1 << 30);
+    let two = Q64::from_i32(2);
+    assert_eq!(big.checked_mul(two), None);
+    assert_eq!(big.checked_mul(-two), Some(Q64::from_i32(i32::MIN)));
+    assert_eq!((-big).checked_mul(-two * two), None);
+    assert_eq!(big.checked_div(Q64::HALF), None);
+    assert_eq!(big.checked_div(Q64::ZERO), None);
+    assert_eq!(big.checked_add(big), None);
+    assert_eq!(big.checked_sub(-big), None);
+    assert_eq!(two.checked_div(-two), Some(-Q64::ONE));
+    assert_eq!(big.saturating_mul(two), Q64::from_bits(i64::MAX));
+    assert_eq!(big.saturating_mul(-two * two), Q64::from_bits(i64::MIN));
+    assert_eq!((-big).saturating_div(Q64::EPSILON), Q64::from_bits(i64::MIN));
+    assert_eq!((-big).saturating_sub(big).saturating_sub(big), Q64::from_bits(i64::MIN));
+    assert_eq!(big.wrapping_mul(two * two), Q64::ZERO);
+    assert_eq!(big.wrapping_add(big), Q64::from_i32(i32::MIN));
+    assert_eq!(big.overflowing_mul(two), (Q64::from_i32(i32::MIN), true));
+    assert_eq!(two.overflowing_div(two), (Q64::ONE, false));
+
+    let v = Q64Vec::new(Q64::ONE, big);
+    assert_eq!(v.checked_mul(two), None);
+    assert_eq!(v.checked_add(v), None);
+    assert_eq!(v.checked_div(two), Some(Q64Vec::new(Q64::HALF, big / two)));
+    assert_eq!(v.saturating_mul(two), Q64Vec::new(two, Q64::from_bits(i64::MAX)));
+    assert_eq!(v.overflowing_sub(-v), (Q64Vec::new(two, Q64::from_i32(i32::MIN)), true));
+    assert_eq!(v.wrapping_mul(two * two), Q64Vec::new(two * two, Q64::ZERO));
+  }
+
   #[test]
   fn test_serialization() {
     let v = Q64Vec::from_f64(123.456, -789.012);
@@ -427,4 +2041,923 @@ mod tests {
     let v2: Q64Vec = serde_json::from_str(&s).unwrap();
     assert_eq!(v, v2);
   }
+
+  #[test]
+  fn test_serde_representations() {
+    #[derive(Debug, PartialEq, ::serde::Serialize, ::serde::Deserialize)]
+    struct Level {
+      #[serde(with = "crate::serde::decimal")]
+      speed: Q64,
+      #[serde(with = "crate::serde::decimal")]
+      spawn: Q64Vec,
+      #[serde(with = "crate::serde::raw_int")]
+      gravity: Q32,
+      #[serde(with = "crate::serde::float_lossy")]
+      scale: Q64,
+      #[serde(with = "crate::serde::struct_form")]
+      exit: Q64Vec,
+      #[serde(with = "crate::serde::raw_int")]
+      wide: Fixed<i128, 64>,
+    }
+
+    let level = Level {
+      speed: Q64::from_f64(-12.375),
+      spawn: Q64Vec::from_f64(0.5, -3.0),
+      gravity: Q32::from_f64(9.75),
+      scale: Q64::from_f64(1.25),
+      exit: Q64Vec::new(Q64::ONE, -Q64::HALF),
+      wide: Fixed::<i128, 64>::from_bits(-(1 << 100)),
+    };
+    let s = serde_json::to_string(&level).unwrap();
+    assert_eq!(
+      s,
+      concat!(
+        r#"{"speed":"-12.375","spawn":{"x":"0.5","y":"-3"},"gravity":638976,"scale":1.25,"#,
+        r#""exit":{"x":"4294967296","y":"-2147483648"},"wide":-1267650600228229401496703205376}"#,
+      ),
+    );
+    assert_eq!(serde_json::from_str::<Level>(&s).unwrap(), level);
+
+    // Hand-written values round to nearest, and bad ones are rejected.
+    let edited = s.replace(r#""speed":"-12.375""#, r#""speed":"0.1""#).replace("1.25", "0.1");
+    let edited: Level = serde_json::from_str(&edited).unwrap();
+    assert_eq!(edited.speed, "0.1".parse().unwrap());
+    assert_eq!(edited.scale, Q64::from_f64_round(0.1));
+    assert!(serde_json::from_str::<Level>(&s.replace("-12.375", "twelve")).is_err());
+    assert!(serde_json::from_str::<Level>(&s.replace("1.25", "1e300")).is_err());
+
+    // The default representations are unchanged.
+    assert_eq!(serde_json::to_string(&Q64::ONE).unwrap(), r#""4294967296""#);
+    assert_eq!(serde_json::to_string(&level.exit).unwrap(), r#""4294967296,-2147483648""#);
+  }
+
+  #[test]
+  fn test_binary_encoding() {
+    use crate::encoding::*;
+
+    let x = Q64::from_f64(-1.5);
+    assert_eq!(Q64::from_le_bytes(x.to_le_bytes()), x);
+    assert_eq!(Q64::ONE.to_le_bytes(), [0, 0, 0, 0, 1, 0, 0, 0]);
+    assert_eq!(Q32::ONE.to_le_bytes(), [0, 0, 1, 0]);
+    let v = Q64Vec::new(Q64::ONE, Q64::EPSILON);
+    assert_eq!(v.to_le_bytes(), [0, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0]);
+    assert_eq!(Q64Vec::from_le_bytes(v.to_le_bytes()), v);
+
+    // Zigzag maps 0, -1, 1, -2, ... to 0, 1, 2, 3, ...
+    for (x, bytes) in [
+      (0, vec![0]),
+      (-1, vec![1]),
+      (1, vec![2]),
+      (-64, vec![127]),
+      (64, vec![128, 1]),
+      (i64::MAX, vec![254, 255, 255, 255, 255, 255, 255, 255, 255, 1]),
+      (i64::MIN, vec![255, 255, 255, 255, 255, 255, 255, 255, 255, 1]),
+    ] {
+      let mut out = Vec::new();
+      write_varint(&mut out, x);
+      assert_eq!(out, bytes);
+      let mut input = &out[..];
+      assert_eq!(read_varint(&mut input), Ok(x));
+      assert!(input.is_empty());
+    }
+    assert_eq!(read_varint(&mut &[0x80][..]), Err(DecodeError::UnexpectedEnd));
+    assert_eq!(read_varint(&mut &[0x80, 0][..]), Err(DecodeError::Invalid));
+    assert_eq!(read_varint(&mut &[255, 255, 255, 255, 255, 255, 255, 255, 255, 2][..]), Err(DecodeError::Invalid));
+
+    let positions: Vec<Q64Vec> = (0..100i64)
+      .map(|i| Q64Vec::new(Q64::from_i32(1000) + Q64::from_bits(i << 22), Q64::from_bits(-i << 24)))
+      .chain([Q64Vec::new(Q64::from_bits(i64::MIN), Q64::from_bits(i64::MAX))])
+      .collect();
+    let mut out = Vec::new();
+    write_q64_vec_deltas(&mut out, &positions);
+    // Steps of 2^-10 and 2^-8 cost four bytes per coordinate, rather than the eight of the raw bits.
+    assert_eq!(out.len(), 1 + 8 + 99 * 8 + 20);
+    let mut input = &out[..];
+    assert_eq!(read_q64_vec_deltas(&mut input), Ok(positions.clone()));
+    assert!(input.is_empty());
+    assert_eq!(read_q64_vec_deltas(&mut &out[..out.len() - 1]), Err(DecodeError::UnexpectedEnd));
+
+    let xs: Vec<Q64> = positions.iter().map(|v| v.x).collect();
+    let mut out = Vec::new();
+    write_q64_deltas(&mut out, &xs);
+    assert_eq!(read_q64_deltas(&mut &out[..]), Ok(xs));
+    // A corrupt count is caught before allocating.
+    assert_eq!(read_q64_deltas(&mut &[255, 255, 255, 255, 15][..]), Err(DecodeError::UnexpectedEnd));
+  }
+
+  #[test]
+  fn test_vec3_and_quat() {
+    let x = Q64Vec3::from_i32(1, 0, 0);
+    let y = Q64Vec3::from_i32(0, 1, 0);
+    let z = Q64Vec3::from_i32(0, 0, 1);
+    assert_eq!(x.cross(y), z);
+    assert_eq!(y.cross(x), -z);
+    let v = Q64Vec3::from_f64(1.5, -2.0, 0.25);
+    assert_eq!(v.dot(v), v.norm_squared());
+    assert_eq!(v.cross(v), Q64Vec3::ZERO);
+    assert_eq!(v.cross(x).dot(v), Q64::ZERO);
+    assert_eq!(Q64Vec3::from_i32(2, 3, 6).norm(), Q64::from_i32(7));
+    assert_eq!(Q64Vec3::from_i32(0, 0, -5).normalized(), -z);
+    assert_eq!(Q64Vec3::ZERO.normalized(), Q64Vec3::ZERO);
+    assert!((v.normalized().norm() - Q64::ONE).abs() <= Q64::from_bits(2));
+    // The squares are computed exactly, so huge coordinates still work.
+    let huge = Q64Vec3::new(Q64::from_bits(i64::MIN), Q64::from_bits(i64::MIN), Q64::ZERO);
+    assert_eq!(huge.norm(), Q64::from_bits(i64::MAX));
+    assert_eq!(huge.normalized().x, Q64::from_f64(-std::f64::consts::FRAC_1_SQRT_2));
+    assert_eq!((v + v - v) * Q64::from_i32(2) / Q64::from_i32(2), v);
+    assert_eq!(v.checked_mul(Q64::from_bits(i64::MAX)), None);
+    assert_eq!(format!("{:.1}", v), "(1.5, -2.0, 0.2)");
+    let s = serde_json::to_string(&v).unwrap();
+    assert_eq!(serde_json::from_str::<Q64Vec3>(&s).unwrap(), v);
+
+    let close = |a: Q64Vec3, b: Q64Vec3| (a - b).norm() < Q64::from_bits(64);
+    let quarter_turn_z = Q64Quat::from_axis_angle(z, Q64::PI_OVER_TWO);
+    assert!(close(quarter_turn_z.rotate(x), y));
+    assert!(close(quarter_turn_z.conjugate().rotate(y), x));
+    let quarter_turn_x = Q64Quat::from_axis_angle(Q64Vec3::from_i32(3, 0, 0), Q64::PI_OVER_TWO);
+    // Composition applies the right-hand rotation first.
+    let composed = quarter_turn_x * quarter_turn_z;
+    assert!(close(composed.rotate(x), z));
+    assert!(close(composed.rotate(v), quarter_turn_x.rotate(quarter_turn_z.rotate(v))));
+    assert!(close(Q64Quat::IDENTITY.rotate(v), v));
+    assert_eq!(Q64Quat::from_axis_angle(Q64Vec3::ZERO, Q64::ONE), Q64Quat::IDENTITY);
+    assert!((composed.norm() - Q64::ONE).abs() < Q64::from_bits(16));
+
+    // Halfway between no rotation and a quarter turn is an eighth turn.
+    let eighth_turn_z = Q64Quat::from_axis_angle(z, Q64::PI_OVER_TWO * Q64::HALF);
+    let halfway = Q64Quat::IDENTITY.slerp(quarter_turn_z, Q64::HALF);
+    assert!(close(halfway.rotate(x), eighth_turn_z.rotate(x)));
+    assert!(close(Q64Quat::IDENTITY.slerp(quarter_turn_z, Q64::ONE).rotate(x), y));
+    assert!(close(Q64Quat::IDENTITY.slerp(quarter_turn_z, Q64::ZERO).rotate(x), x));
+    // The shorter arc is taken even when the quaternions have opposite signs.
+    assert!(close(Q64Quat::IDENTITY.slerp(-quarter_turn_z, Q64::HALF).rotate(x), eighth_turn_z.rotate(x)));
+    // Nearly equal rotations interpolate without blowing up.
+    let tiny_turn = Q64Quat::from_axis_angle(z, Q64::from_bits(1000));
+    let tiny_half_turn = Q64Quat::from_axis_angle(z, Q64::from_bits(500));
+    assert!(close(Q64Quat::IDENTITY.slerp(tiny_turn, Q64::HALF).rotate(x), tiny_half_turn.rotate(x)));
+  }
+
+  #[test]
+  fn test_vec_geometry() {
+    let a = Q64Vec::from_i32(3, 4);
+    let b = Q64Vec::from_i32(-2, 1);
+    assert_eq!(a.dot(b), Q64::from_i32(-2));
+    assert_eq!(a.cross(b), Q64::from_i32(11));
+    assert_eq!(b.cross(a), Q64::from_i32(-11));
+    assert_eq!(a.perp(), Q64Vec::from_i32(-4, 3));
+    assert_eq!(a.perp().dot(a), Q64::ZERO);
+    assert_eq!(a.distance(b), Q64::from_f64(34f64.sqrt()));
+    assert_eq!(a.distance_squared(b), Q64::from_i32(34));
+    assert_eq!(a.lerp(b, Q64::HALF), Q64Vec::from_f64(0.5, 2.5));
+    assert_eq!(a.lerp(b, Q64::ONE), b);
+
+    let close = |u: Q64Vec, v: Q64Vec| (u - v).norm() < Q64::from_bits(16);
+    assert!(close(a.rotate(Q64::PI_OVER_TWO), a.perp()));
+    assert!(close(a.rotate(Q64::PI), -a));
+    assert!(close(a.rotate(-Q64::PI_OVER_TWO).rotate(Q64::PI_OVER_TWO), a));
+    let eighth_turn = Q64::PI_OVER_TWO * Q64::HALF;
+    assert_eq!(a.rotate_by(eighth_turn.angle_to_vec()), a.rotate(eighth_turn));
+    assert_eq!(a.rotate_by(Q64Vec::from_i32(0, 1)), a.perp());
+
+    let x_axis = Q64Vec::from_i32(1, 0);
+    assert_eq!(a.project_onto(Q64Vec::from_i32(5, 0)), Q64Vec::from_i32(3, 0));
+    assert_eq!(a.project_onto(Q64Vec::ZERO), Q64Vec::ZERO);
+    // Far beyond where the squared norm overflows, and far below where it rounds to zero.
+    let far = Q64Vec::from_i32(60000, 0);
+    assert_eq!(far.project_onto(Q64Vec::from_i32(50000, 50000)), Q64Vec::from_i32(30000, 30000));
+    assert_eq!(far.project_onto(Q64Vec::from_i32(-1_000_000_000, 0)), far);
+    assert_eq!(a.project_onto(Q64Vec::new(Q64::EPSILON, Q64::ZERO)), Q64Vec::from_i32(3, 0));
+    assert_eq!(a.project_onto(Q64Vec::new(Q64::EPSILON, Q64::EPSILON)), Q64Vec::from_f64(3.5, 3.5));
+    assert_eq!(a.project_onto(Q64Vec::new(-Q64::EPSILON, Q64::EPSILON)), Q64Vec::from_f64(-0.5, 0.5));
+    let extreme = Q64Vec::new(Q64::from_bits(i64::MIN), Q64::from_bits(i64::MIN));
+    assert_eq!(extreme.project_onto(extreme), extreme);
+    for i in 1..=1000i64 {
+      let bits = |k: i64| i.wrapping_mul(k).wrapping_mul(0x9e3779b97f4a7c15u64 as i64);
+      let v = Q64Vec::new(Q64::from_bits(bits(1) >> 16), Q64::from_bits(bits(3) >> 16));
+      let onto = Q64Vec::new(Q64::from_bits(bits(5) >> 8), Q64::from_bits(bits(7) >> 8));
+      let ((vx, vy), (ox, oy)) = (v.to_f64(), onto.to_f64());
+      let t = (vx * ox + vy * oy) / (ox * ox + oy * oy);
+      let (px, py) = v.project_onto(onto).to_f64();
+      assert!((px - ox * t).abs() < 1e-6 && (py - oy * t).abs() < 1e-6);
+    }
+    assert_eq!(a.reflect(x_axis), Q64Vec::from_i32(-3, 4));
+    assert_eq!(a.reflect(Q64Vec::from_i32(0, -1)), Q64Vec::from_i32(3, -4));
+
+    assert_eq!(a.clamp_length(Q64::from_i32(10)), a);
+    assert_eq!(a.clamp_length(Q64::ONE), a.normalized());
+    assert!(a.clamp_length(Q64::from_i32(2)).norm() <= Q64::from_i32(2));
+    assert_eq!(a.clamp_length(Q64::ZERO), Q64Vec::ZERO);
+    assert_eq!(a.clamp_length(-Q64::ONE), Q64Vec::ZERO);
+    assert_eq!(Q64Vec::ZERO.clamp_length(-Q64::ONE), Q64Vec::ZERO);
+
+    assert_eq!(x_axis.angle_between(Q64Vec::from_i32(0, 7)), Q64::FRAC_PI_2);
+    assert_eq!(x_axis.angle_between(Q64Vec::from_i32(0, -7)), Q64::FRAC_PI_2);
+    assert_eq!(x_axis.angle_between(Q64Vec::from_i32(-2, 0)), Q64::PI_ROUNDED);
+    assert_eq!(a.angle_between(a * Q64::from_i32(3)), Q64::ZERO);
+    assert_eq!(a.angle_between(Q64Vec::ZERO), Q64::ZERO);
+    let extreme = Q64Vec::new(Q64::from_bits(i64::MIN), Q64::from_bits(i64::MIN));
+    assert_eq!(extreme.angle_between(x_axis), Q64::ONE.atan2(-Q64::ONE));
+  }
+
+  #[test]
+  fn test_norm_extremes() {
+    let max = Q64::from_bits(i64::MAX);
+    let min = Q64::from_bits(i64::MIN);
+    // Past the point where the old implementation gave up.
+    let far = Q64Vec::from_i32(300_000, -400_000);
+    assert_eq!(far.norm(), Q64::from_i32(500_000));
+    assert_eq!(far.normalized(), Q64Vec::from_f64(0.6, -0.8));
+    assert_eq!(Q64Vec::from_i32(0, -2_000_000_000).norm(), Q64::from_i32(2_000_000_000));
+    assert_eq!(Q64Vec::new(max, Q64::ZERO).norm(), max);
+    assert_eq!(Q64Vec::new(Q64::ZERO, max).normalized(), Q64Vec::from_i32(0, 1));
+    assert_eq!(Q64Vec::new(min, Q64::ZERO).normalized(), Q64Vec::new(-Q64::ONE, Q64::ZERO));
+    // sqrt(2) * 2^31 doesn't fit, so the norm saturates, but normalizing still works.
+    assert_eq!(Q64Vec::new(min, min).norm(), max);
+    let diagonal = Q64Vec::new(max, min).normalized();
+    assert!((diagonal.norm() - Q64::ONE).abs() <= Q64::from_bits(2));
+    assert_eq!(diagonal.x, -diagonal.y);
+    // And at the small end, where squaring in Q64 would underflow to zero.
+    let tiny = Q64Vec::new(Q64::EPSILON, Q64::EPSILON);
+    assert_eq!(tiny.norm(), Q64::EPSILON);
+    assert_eq!(tiny.normalized(), Q64Vec::new(diagonal.x, diagonal.x));
+    assert_eq!(Q64Vec::new(Q64::ZERO, -Q64::EPSILON).normalized(), Q64Vec::from_i32(0, -1));
+  }
+
+  #[test]
+  fn test_matrices() {
+    let a = Q64Mat2::from_cols(Q64Vec::from_i32(2, 1), Q64Vec::from_i32(-1, 3));
+    let p = Q64Vec::from_f64(0.5, -4.0);
+    assert_eq!(a.determinant(), Q64::from_i32(7));
+    assert_eq!(a * Q64Vec::from_i32(1, 0), a.x_axis);
+    assert_eq!(a * p, Q64Vec::from_f64(5.0, -11.5));
+    assert_eq!(a.transpose().transpose(), a);
+    assert_eq!(a * Q64Mat2::IDENTITY, a);
+    let inverse = a.try_inverse().unwrap();
+    let close = |u: Q64Vec, v: Q64Vec| (u - v).norm() < Q64::from_bits(16);
+    assert!(close(inverse * (a * p), p));
+    assert!(close((inverse * a) * p, p));
+    let scale = Q64Mat2::from_scale(Q64Vec::from_i32(2, 4));
+    assert_eq!(scale.try_inverse(), Ok(Q64Mat2::from_scale(Q64Vec::from_f64(0.5, 0.25))));
+    let singular = Q64Mat2::from_cols(Q64Vec::from_i32(1, 2), Q64Vec::from_i32(2, 4));
+    assert_eq!(singular.try_inverse(), Err(FixedError::Singular));
+    let tiny = Q64Mat2::from_scale(Q64Vec::new(Q64::EPSILON, Q64::ONE));
+    assert_eq!(tiny.try_inverse(), Err(FixedError::Overflow));
+    assert!(close(Q64Mat2::from_angle(Q64::PI_OVER_TWO) * p, p.perp()));
+
+    let m = Q64Mat3::from_cols(
+      Q64Vec3::from_i32(1, 0, 2),
+      Q64Vec3::from_i32(-1, 3, 0),
+      Q64Vec3::from_i32(0, 1, 4),
+    );
+    assert_eq!(m.determinant(), Q64::from_i32(10));
+    assert_eq!(m.transpose().determinant(), Q64::from_i32(10));
+    let v = Q64Vec3::from_f64(1.5, -2.0, 0.25);
+    let m_inverse = m.try_inverse().unwrap();
+    assert!((m_inverse * (m * v) - v).norm() < Q64::from_bits(16));
+    assert!(((m * m_inverse) * v - v).norm() < Q64::from_bits(16));
+    assert_eq!(Q64Mat3::IDENTITY.try_inverse(), Ok(Q64Mat3::IDENTITY));
+    let flat = Q64Mat3::from_cols(v, v * Q64::from_i32(2), Q64Vec3::from_i32(0, 0, 1));
+    assert_eq!(flat.try_inverse(), Err(FixedError::Singular));
+
+    // Scale, then rotate a quarter turn, then translate.
+    let t = Q64Vec::from_i32(10, -5);
+    let affine = Q64Affine2::from_scale_angle_translation(Q64Vec::from_i32(2, 2), Q64::PI_OVER_TWO, t);
+    assert!(close(affine.transform_point(Q64Vec::from_i32(1, 0)), Q64Vec::from_i32(10, -3)));
+    assert!(close(affine.transform_vector(Q64Vec::from_i32(1, 0)), Q64Vec::from_i32(0, 2)));
+    assert!((affine.determinant() - Q64::from_i32(4)).abs() < Q64::from_bits(16));
+    let affine_inverse = affine.try_inverse().unwrap();
+    assert!(close(affine_inverse.transform_point(affine.transform_point(p)), p));
+    assert!(close((affine * affine_inverse).transform_point(p), p));
+    // Composition applies the right-hand transform first.
+    let shift = Q64Affine2::from_translation(Q64Vec::from_i32(1, 1));
+    assert_eq!((shift * affine).transform_point(p), shift.transform_point(affine.transform_point(p)));
+    assert_eq!((affine * shift).translation, affine.transform_point(Q64Vec::from_i32(1, 1)));
+    assert_eq!(Q64Affine2::from_scale(Q64Vec::ZERO).try_inverse(), Err(FixedError::Singular));
+    // The homogeneous form agrees.
+    let h = affine.to_mat3();
+    assert_eq!(h.try_transform_point(p), Ok(affine.transform_point(p)));
+    assert_eq!(h.transform_vector(p), affine.transform_vector(p));
+    assert_eq!((h * shift.to_mat3()).try_transform_point(p), Ok((affine * shift).transform_point(p)));
+  }
+
+  #[test]
+  fn test_geometry() {
+    use crate::geometry::*;
+    let v = |x: f64, y: f64| Q64Vec::from_f64(x, y);
+    let q = Q64::from_f64;
+
+    let aabb = Aabb::new(v(2.0, 1.0), v(-1.0, -1.0));
+    assert_eq!((aabb.min, aabb.max), (v(-1.0, -1.0), v(2.0, 1.0)));
+    assert_eq!(aabb.center(), v(0.5, 0.0));
+    assert_eq!(aabb.half_extents(), v(1.5, 1.0));
+    assert!(aabb.contains_point(v(2.0, 1.0)));
+    assert!(!aabb.contains_point(v(2.5, 0.0)));
+    assert_eq!(aabb.closest_point(v(5.0, -3.0)), v(2.0, -1.0));
+    // Touching counts as overlapping.
+    assert!(aabb.overlaps(&Aabb::new(v(2.0, 0.0), v(3.0, 3.0))));
+    assert!(!aabb.overlaps(&Aabb::new(v(2.5, 0.0), v(3.0, 3.0))));
+    assert_eq!(Aabb::from_points(&[v(1.0, 5.0), v(-2.0, 0.0), v(0.0, -1.0)]), Some(Aabb::new(v(-2.0, -1.0), v(1.0, 5.0))));
+    assert_eq!(aabb.expanded(Q64::ONE), Aabb::new(v(-2.0, -2.0), v(3.0, 2.0)));
+
+    let circle = Circle::new(v(0.0, 3.0), q(1.5));
+    assert!(circle.overlaps(&Circle::new(v(0.0, 0.0), q(1.5))));
+    assert!(!circle.overlaps(&Circle::new(v(0.0, 0.0), q(1.25))));
+    assert!(!aabb.overlaps_circle(&circle));
+    assert!(aabb.overlaps_circle(&Circle::new(v(0.0, 3.0), q(2.0))));
+    assert!(!aabb.overlaps_circle(&Circle::new(v(3.0, 3.0), Q64::ONE)));
+    assert_eq!(circle.closest_point(v(0.0, 10.0)), v(0.0, 4.5));
+    assert_eq!(circle.aabb(), Aabb::new(v(-1.5, 1.5), v(1.5, 4.5)));
+    // Circles far apart don't overflow.
+    let far = Circle::new(v(1e9, -1e9), Q64::ONE);
+    assert!(!far.overlaps(&Circle::new(v(-1e9, 1e9), Q64::ONE)));
+    // Nor do circles 2^31 or more apart, whose `Q64Vec::distance` would.
+    let (left, right) = (Circle::new(v(-1.5e9, 0.0), Q64::ONE), Circle::new(v(1.5e9, 0.0), q(2e9)));
+    assert!(!left.contains_point(right.center) && !left.overlaps(&right));
+    assert!(right.overlaps(&Circle::new(left.center, q(1e9))));
+    assert!(!right.overlaps(&Circle::new(left.center, q(0.9e9))));
+
+    let segment = Segment::new(v(0.0, 0.0), v(4.0, 0.0));
+    assert_eq!(segment.closest_point(v(1.0, 3.0)), v(1.0, 0.0));
+    assert_eq!(segment.closest_point(v(-1.0, 3.0)), v(0.0, 0.0));
+    assert_eq!(segment.distance_to_point(v(6.0, 0.0)), q(2.0));
+    assert_eq!(segment.intersection(&Segment::new(v(1.0, -1.0), v(3.0, 1.0))), Some(v(2.0, 0.0)));
+    assert_eq!(segment.intersection(&Segment::new(v(4.0, 0.0), v(5.0, 1.0))), Some(v(4.0, 0.0)));
+    assert_eq!(segment.intersection(&Segment::new(v(5.0, -1.0), v(5.0, 1.0))), None);
+    assert_eq!(segment.intersection(&Segment::new(v(0.0, 1.0), v(4.0, 1.0))), None);
+    assert_eq!(segment.intersection(&Segment::new(v(6.0, 0.0), v(3.0, 0.0))), Some(v(3.0, 0.0)));
+    assert_eq!(segment.intersection(&Segment::new(v(5.0, 0.0), v(6.0, 0.0))), None);
+    assert_eq!(segment.intersection(&Segment::new(v(2.0, 0.0), v(2.0, 0.0))), Some(v(2.0, 0.0)));
+
+    let ray = Ray::new(v(-5.0, 0.0), v(2.0, 0.0));
+    let hit = ray.cast_circle(&Circle::new(v(0.0, 0.0), Q64::ONE)).unwrap();
+    assert_eq!((hit.t, hit.point, hit.normal), (q(2.0), v(-1.0, 0.0), v(-1.0, 0.0)));
+    assert_eq!(ray.cast_circle(&Circle::new(v(0.0, 2.0), q(2.0))).map(|hit| hit.point), Some(v(0.0, 0.0)));
+    assert_eq!(ray.cast_circle(&Circle::new(v(0.0, 3.0), Q64::ONE)), None);
+    assert_eq!(ray.cast_circle(&Circle::new(v(-9.0, 0.0), Q64::ONE)), None);
+    let inside = ray.cast_circle(&Circle::new(v(-5.0, 0.5), Q64::ONE)).unwrap();
+    assert_eq!((inside.t, inside.normal), (Q64::ZERO, Q64Vec::ZERO));
+
+    let hit = ray.cast_aabb(&aabb).unwrap();
+    assert_eq!((hit.t, hit.point, hit.normal), (q(2.0), v(-1.0, 0.0), v(-1.0, 0.0)));
+    let from_above = Ray::new(v(0.0, 5.0), v(0.25, -1.0)).cast_aabb(&aabb).unwrap();
+    assert_eq!((from_above.t, from_above.point, from_above.normal), (q(4.0), v(1.0, 1.0), v(0.0, 1.0)));
+    assert_eq!(Ray::new(v(0.0, 5.0), v(0.0, 1.0)).cast_aabb(&aabb), None);
+    assert_eq!(Ray::new(v(3.0, 5.0), v(0.0, -1.0)).cast_aabb(&aabb), None);
+    assert_eq!(Ray::new(v(0.0, 0.0), v(1.0, 1.0)).cast_aabb(&aabb).map(|hit| hit.t), Some(Q64::ZERO));
+
+    let hit = ray.cast_segment(&Segment::new(v(-1.0, -1.0), v(-1.0, 1.0))).unwrap();
+    assert_eq!((hit.t, hit.point, hit.normal), (q(2.0), v(-1.0, 0.0), v(-1.0, 0.0)));
+    assert_eq!(ray.cast_segment(&Segment::new(v(-6.0, -1.0), v(-6.0, 1.0))), None);
+    assert_eq!(ray.cast_segment(&Segment::new(v(0.0, 1.0), v(0.0, 2.0))), None);
+    assert_eq!(ray.cast_segment(&segment), None);
+
+    // A diamond, given clockwise.
+    let diamond = Polygon::new(vec![v(0.0, 2.0), v(2.0, 0.0), v(0.0, -2.0), v(-2.0, 0.0)]).unwrap();
+    assert_eq!(diamond.vertices()[0], v(-2.0, 0.0));
+    assert!(diamond.contains_point(v(1.0, 1.0)));
+    assert!(!diamond.contains_point(v(1.5, 1.0)));
+    assert_eq!(diamond.closest_point(v(2.0, 2.0)), v(1.0, 1.0));
+    assert_eq!(diamond.closest_point(v(5.0, 0.0)), v(2.0, 0.0));
+    assert_eq!(diamond.aabb(), Aabb::new(v(-2.0, -2.0), v(2.0, 2.0)));
+    assert!(diamond.overlaps_aabb(&Aabb::new(v(1.0, 1.0), v(3.0, 3.0))));
+    assert!(!diamond.overlaps_aabb(&Aabb::new(v(1.5, 1.5), v(3.0, 3.0))));
+    assert!(diamond.overlaps_circle(&Circle::new(v(2.0, 2.0), q(1.5))));
+    assert!(!diamond.overlaps_circle(&Circle::new(v(2.0, 2.0), q(1.25))));
+    let shifted = diamond.transformed(&Q64Affine2::from_translation(v(3.0, 0.0))).unwrap();
+    assert!(diamond.overlaps(&shifted));
+    assert!(!diamond.overlaps(&diamond.transformed(&Q64Affine2::from_translation(v(4.5, 0.0))).unwrap()));
+    let hit = ray.cast_polygon(&diamond).unwrap();
+    assert_eq!((hit.t, hit.point), (q(1.5), v(-2.0, 0.0)));
+    let hit = Ray::new(v(-3.0, 1.0), v(1.0, 0.0)).cast_polygon(&diamond).unwrap();
+    assert_eq!((hit.t, hit.point), (q(2.0), v(-1.0, 1.0)));
+    assert_eq!(hit.normal, v(-1.0, 1.0).normalized());
+    assert_eq!(Ray::new(v(-3.0, 3.0), v(1.0, 0.0)).cast_polygon(&diamond), None);
+    assert_eq!(Ray::new(v(-3.0, 0.0), v(-1.0, 0.0)).cast_polygon(&diamond), None);
+
+    assert_eq!(Polygon::new(vec![v(0.0, 0.0), v(1.0, 0.0)]), None);
+    assert_eq!(Polygon::new(vec![v(0.0, 0.0), v(1.0, 0.0), v(2.0, 0.0)]), None);
+    // Not convex: an arrowhead, and a pentagram, which turns the same way at every vertex.
+    assert_eq!(Polygon::new(vec![v(0.0, 0.0), v(2.0, 1.0), v(0.0, 2.0), v(1.0, 1.0)]), None);
+    let star = [0, 2, 4, 1, 3].map(|i| (Q64::PI_TIMES_2 * Q64::from_i32(i) / Q64::from_i32(5)).angle_to_vec());
+    assert_eq!(Polygon::new(star.to_vec()), None);
+    assert!(Polygon::new(vec![v(0.0, 0.0), v(1.0, 0.0), v(2.0, 0.0), v(1.0, 1.0)]).is_some());
+  }
+
+  #[test]
+  fn test_rng() {
+    let mut rng = Q64Rng::new(42);
+    // Pinned, so that any change to the stream is noticed.
+    assert_eq!(Q64Rng::new(0).next_u64(), 11091344671253066420);
+    assert_ne!(Q64Rng::new(1).next_u64(), Q64Rng::new(0).next_u64());
+
+    let n = 4096;
+    let units: Vec<Q64> = (0..n).map(|_| rng.next_q64_unit()).collect();
+    assert!(units.iter().all(|&u| Q64::ZERO <= u && u < Q64::ONE));
+    let mean = units.iter().fold(Q64::ZERO, |sum, &u| sum + u) / Q64::from_i32(n);
+    assert!((mean - Q64::HALF).abs() < Q64::from_f64(0.02));
+
+    let (lo, hi) = (Q64::from_f64(-2.5), Q64::from_f64(-2.25));
+    assert!((0..n).map(|_| rng.range(lo, hi)).all(|x| lo <= x && x < hi));
+    let tiny = (Q64::ONE, Q64::ONE + Q64::EPSILON);
+    assert!((0..16).all(|_| rng.range(tiny.0, tiny.1) == Q64::ONE));
+    let (min, max) = (Q64::from_bits(i64::MIN), Q64::from_bits(i64::MAX));
+    assert!((0..16).any(|_| rng.range(min, max) < Q64::ZERO));
+
+    for _ in 0..64 {
+      assert!((rng.unit_vector().norm() - Q64::ONE).abs() < Q64::from_bits(4));
+    }
+
+    let samples: Vec<Q64> = (0..n).map(|_| rng.gaussian(Q64::from_i32(10), Q64::from_i32(2))).collect();
+    let mean = samples.iter().fold(Q64::ZERO, |sum, &x| sum + x) / Q64::from_i32(n);
+    let variance = samples.iter().fold(Q64::ZERO, |sum, &x| sum + (x - mean) * (x - mean)) / Q64::from_i32(n);
+    assert!((mean - Q64::from_i32(10)).abs() < Q64::from_f64(0.1));
+    assert!((variance - Q64::from_i32(4)).abs() < Q64::from_f64(0.3));
+
+    // A snapshot resumes the same stream.
+    let s = serde_json::to_string(&rng).unwrap();
+    let mut restored: Q64Rng = serde_json::from_str(&s).unwrap();
+    assert_eq!(restored, rng);
+    assert_eq!((0..8).map(|_| restored.next_u64()).collect::<Vec<_>>(), (0..8).map(|_| rng.next_u64()).collect::<Vec<_>>());
+    assert!(serde_json::from_str::<Q64Rng>(r#""0,0,0,0""#).is_err());
+    assert!(serde_json::from_str::<Q64Rng>(r#""1,2,3""#).is_err());
+  }
+
+  #[test]
+  #[should_panic(expected = "empty range")]
+  fn test_rng_empty_range() {
+    Q64Rng::new(0).range(Q64::ONE, Q64::ONE);
+  }
+
+  #[test]
+  fn test_sum_product_rem() {
+    let values = [Q64::from_i32(3), Q64::HALF, -Q64::from_i32(2)];
+    assert_eq!(values.iter().sum::<Q64>(), Q64::from_f64(1.5));
+    assert_eq!(values.into_iter().product::<Q64>(), -Q64::from_i32(3));
+    assert_eq!(std::iter::empty::<Q64>().sum::<Q64>(), Q64::ZERO);
+    assert_eq!(std::iter::empty::<Q64>().product::<Q64>(), Q64::ONE);
+    let vectors = [Q64Vec::from_i32(1, 2), Q64Vec::from_i32(-4, 5)];
+    assert_eq!(vectors.iter().sum::<Q64Vec>(), Q64Vec::from_i32(-3, 7));
+    assert_eq!(vectors.into_iter().sum::<Q64Vec>(), Q64Vec::from_i32(-3, 7));
+    let vectors3 = [Q64Vec3::new(Q64::ONE, Q64::ZERO, Q64::HALF); 4];
+    assert_eq!(vectors3.iter().sum::<Q64Vec3>(), Q64Vec3::new(Q64::from_i32(4), Q64::ZERO, Q64::from_i32(2)));
+
+    // Truncating remainder, like the integers, as opposed to rem_euclid.
+    let x = Q64::from_f64(-7.25);
+    assert_eq!(x % Q64::from_i32(2), Q64::from_f64(-1.25));
+    assert_eq!(x.rem_euclid(Q64::from_i32(2)), Q64::from_f64(0.75));
+    let mut y = Q64::from_f64(5.5);
+    y %= Q64::from_f64(1.5);
+    assert_eq!(y, Q64::ONE);
+  }
+
+  #[test]
+  fn test_physics() {
+    use crate::geometry::{Aabb, Polygon};
+    use crate::physics::{overlapping_pairs, Body, Collider, World};
+
+    let boxes = [
+      Aabb::new(Q64Vec::from_i32(0, 0), Q64Vec::from_i32(2, 2)),
+      Aabb::new(Q64Vec::from_i32(5, 0), Q64Vec::from_i32(6, 1)),
+      Aabb::new(Q64Vec::from_i32(1, 1), Q64Vec::from_i32(5, 3)),
+      Aabb::new(Q64Vec::from_i32(1, 5), Q64Vec::from_i32(2, 6)),
+    ];
+    assert_eq!(overlapping_pairs(&boxes), vec![(0, 2), (1, 2)]);
+
+    // Equal masses colliding head-on and elastically swap velocities exactly.
+    let mut world = World::new(Q64::ONE / Q64::from_i32(60));
+    let ball = |x: i32, vx: i32| Body {
+      position: Q64Vec::from_i32(x, 0),
+      velocity: Q64Vec::from_i32(vx, 0),
+      restitution: Q64::ONE,
+      ..Body::new(Collider::Circle { radius: Q64::ONE }, Q64::ONE)
+    };
+    let left = world.add_body(ball(-3, 6));
+    let right = world.add_body(ball(3, -6));
+    while world.contacts().is_empty() {
+      world.step();
+    }
+    assert_eq!(world.body(left).velocity, Q64Vec::from_i32(-6, 0));
+    assert_eq!(world.body(right).velocity, Q64Vec::from_i32(6, 0));
+    assert_eq!(world.contacts()[0].normal, Q64Vec::from_i32(1, 0));
+
+    // A ball and a box dropped onto static ground come to rest on it without tipping.
+    let mut world = World::new(Q64::ONE / Q64::from_i32(60));
+    world.gravity = Q64Vec::from_i32(0, -10);
+    let ground = world.add_body(Body {
+      position: Q64Vec::from_i32(0, -1),
+      friction: Q64::HALF,
+      ..Body::new_static(Collider::Aabb { half_extents: Q64Vec::from_i32(20, 1) })
+    });
+    let ball = world.add_body(Body {
+      position: Q64Vec::from_i32(-5, 4),
+      restitution: Q64::HALF,
+      ..Body::new(Collider::Circle { radius: Q64::ONE }, Q64::ONE)
+    });
+    let square = Polygon::new(vec![
+      Q64Vec::from_i32(-1, -1),
+      Q64Vec::from_i32(1, -1),
+      Q64Vec::from_i32(1, 1),
+      Q64Vec::from_i32(-1, 1),
+    ])
+    .unwrap();
+    let crate_ = world.add_body(Body {
+      position: Q64Vec::from_i32(5, 3),
+      friction: Q64::HALF,
+      ..Body::new(Collider::Polygon(square), Q64::from_i32(2))
+    });
+    for _ in 0..600 {
+      world.step();
+    }
+    let resting = |id| {
+      let body: &Body = world.body(id);
+      (body.position.y - Q64::ONE).abs() < Q64::from_f64(0.02) && body.velocity.norm() < Q64::from_f64(0.2)
+    };
+    assert!(resting(ball) && resting(crate_));
+    let angle = world.body(crate_).angle;
+    assert!(angle.min(Q64::PI_TIMES_2 - angle) < Q64::from_f64(0.01));
+    assert_eq!(world.body(ground).position, Q64Vec::from_i32(0, -1));
+    assert!(world.contacts().iter().all(|contact| contact.a == ground && contact.normal == Q64Vec::from_i32(0, 1)));
+
+    // A static body with a velocity moves and pushes dynamic ones.
+    let mut world = World::new(Q64::ONE / Q64::from_i32(60));
+    let pusher = world.add_body(Body {
+      position: Q64Vec::from_i32(-3, 0),
+      velocity: Q64Vec::from_i32(2, 0),
+      ..Body::new_static(Collider::Aabb { half_extents: Q64Vec::from_i32(1, 1) })
+    });
+    let pushed = world.add_body(Body::new(Collider::Circle { radius: Q64::ONE }, Q64::ONE));
+    for _ in 0..120 {
+      world.step();
+    }
+    // Off by the rounding of the timestep.
+    assert!(world.body(pusher).position.distance(Q64Vec::from_i32(1, 0)) < Q64::from_f64(1e-6));
+    assert!(world.body(pushed).position.x >= Q64::from_f64(1.9));
+
+    // Large bodies, whose inertia overflows a Q64 if computed directly, still settle, and tiny ones,
+    // whose inverse mass saturates, step without overflowing.
+    let mut world = World::new(Q64::ONE / Q64::from_i32(60));
+    world.gravity = Q64Vec::from_i32(0, -10);
+    world.add_body(Body {
+      position: Q64Vec::from_i32(0, -10),
+      friction: Q64::HALF,
+      ..Body::new_static(Collider::Aabb { half_extents: Q64Vec::from_i32(2000, 10) })
+    });
+    let corners = [(-100, -100), (100, -100), (100, 100), (-100, 100)];
+    let big_square = Polygon::new(corners.iter().map(|&(x, y)| Q64Vec::from_i32(x, y)).collect()).unwrap();
+    let big = world.add_body(Body {
+      position: Q64Vec::from_i32(0, 150),
+      angle: Q64::from_f64(0.1),
+      friction: Q64::HALF,
+      ..Body::new(Collider::Polygon(big_square), Q64::ONE)
+    });
+    let wheel = world.add_body(Body {
+      position: Q64Vec::from_i32(-500, 400),
+      friction: Q64::HALF,
+      ..Body::new(Collider::Circle { radius: Q64::from_i32(300) }, Q64::ONE)
+    });
+    let speck = world.add_body(Body {
+      position: Q64Vec::from_i32(500, 1),
+      ..Body::new(Collider::Circle { radius: Q64::from_f64(0.001) }, Q64::EPSILON)
+    });
+    for _ in 0..600 {
+      world.step();
+    }
+    let height = |id| world.body(id).position.y;
+    assert!((height(big) - Q64::from_i32(100)).abs() < Q64::ONE);
+    assert!((height(wheel) - Q64::from_i32(300)).abs() < Q64::ONE);
+    assert_eq!(world.body(speck).position.x, Q64::from_i32(500));
+
+    // Negative masses and frictions are rejected up front.
+    let antimatter = Body::new(Collider::Circle { radius: Q64::ONE }, -Q64::ONE);
+    assert_eq!(world.try_add_body(antimatter), Err(FixedError::OutOfDomain));
+    let slippery = Body {
+      friction: -Q64::HALF,
+      ..Body::new(Collider::Circle { radius: Q64::ONE }, Q64::ONE)
+    };
+    assert_eq!(world.try_add_body(slippery), Err(FixedError::OutOfDomain));
+    assert_eq!(world.bodies().len(), 4);
+  }
+
+  #[test]
+  #[should_panic(expected = "body mass and friction must not be negative")]
+  fn test_physics_negative_friction() {
+    use crate::physics::{Body, Collider, World};
+    let mut world = World::new(Q64::ONE / Q64::from_i32(60));
+    let body = world.add_body(Body::new(Collider::Circle { radius: Q64::ONE }, Q64::ONE));
+    world.body_mut(body).friction = -Q64::ONE;
+    world.step();
+  }
+
+  #[test]
+  fn test_spatial() {
+    use crate::geometry::Aabb;
+    use crate::spatial::{KdTree, SpatialGrid};
+
+    let mut rng = Q64Rng::new(7);
+    let mut points: Vec<(Q64Vec, usize)> = (0..500)
+      .map(|i| (Q64Vec::new(rng.range(-Q64::from_i32(50), Q64::from_i32(50)), rng.range(Q64::ZERO, Q64::from_i32(20))), i))
+      .collect();
+    // Duplicates and points on the query boundaries exercise the tie-breaking.
+    points.push((points[10].0, 500));
+    points.push((Q64Vec::from_i32(3, 4), 501));
+    points.push((Q64Vec::from_i32(-3, 4), 502));
+    points.push((Q64Vec::from_i32(0, 5), 503));
+
+    let mut grid = SpatialGrid::new(Q64::from_i32(4));
+    for &(position, i) in &points {
+      grid.insert(position, i);
+    }
+    let tree = KdTree::new(points.clone());
+    assert_eq!((grid.len(), tree.len()), (504, 504));
+
+    let ids = |found: Vec<(Q64Vec, &usize)>| found.into_iter().map(|(_, &i)| i).collect::<Vec<_>>();
+    let by_distance = |center: Q64Vec| {
+      let mut sorted = points.clone();
+      sorted.sort_by_key(|&(position, i)| (position.distance(center), i));
+      sorted
+    };
+    for center in [Q64Vec::ZERO, points[10].0, Q64Vec::from_f64(-49.5, 19.25), Q64Vec::from_i32(1000, -1000)] {
+      for radius in [Q64::ZERO, Q64::from_i32(5), Q64::from_f64(12.5)] {
+        let expected: Vec<usize> =
+          by_distance(center).into_iter().filter(|&(p, _)| p.distance(center) <= radius).map(|(_, i)| i).collect();
+        assert_eq!(ids(grid.within_radius(center, radius)), expected);
+        assert_eq!(ids(tree.within_radius(center, radius)), expected);
+      }
+      for k in [0, 1, 7, 600] {
+        let expected: Vec<usize> = by_distance(center).into_iter().take(k).map(|(_, i)| i).collect();
+        assert_eq!(ids(grid.nearest(center, k)), expected);
+        assert_eq!(ids(tree.nearest(center, k)), expected);
+      }
+    }
+    // The points at distance exactly 5 from the origin come last, in the order they were added.
+    assert!(ids(grid.within_radius(Q64Vec::ZERO, Q64::from_i32(5))).ends_with(&[501, 502, 503]));
+    assert_eq!(ids(tree.nearest(points[10].0, 2)), vec![10, 500]);
+
+    let aabb = Aabb::new(Q64Vec::from_i32(-10, 2), Q64Vec::from_i32(3, 5));
+    let expected: Vec<usize> = points.iter().filter(|&&(p, _)| aabb.contains_point(p)).map(|&(_, i)| i).collect();
+    assert!(expected.contains(&501));
+    assert_eq!(ids(grid.within_aabb(&aabb)), expected);
+    assert_eq!(ids(tree.within_aabb(&aabb)), expected);
+
+    // Points more than Q64::MAX apart, whose Q64Vec::distance overflows.
+    let (a, b) = (Q64Vec::from_f64(-1.5e9, 0.0), Q64Vec::from_f64(1.5e9, 0.0));
+    let mut far = SpatialGrid::new(Q64::ONE);
+    far.insert(a, 0);
+    far.insert(b, 1);
+    let far_tree = KdTree::new(vec![(a, 0), (b, 1)]);
+    assert_eq!(ids(far.nearest(a, 2)), vec![0, 1]);
+    assert_eq!(ids(far.nearest(b, 2)), vec![1, 0]);
+    assert_eq!(ids(far_tree.nearest(a, 2)), vec![0, 1]);
+    assert_eq!(ids(far_tree.nearest(b, 1)), vec![1]);
+    assert_eq!(ids(far.within_radius(a, Q64::MAX)), vec![0]);
+    assert_eq!(ids(far_tree.within_radius(a, Q64::MAX)), vec![0]);
+    assert_eq!(ids(far_tree.within_radius(Q64Vec::ZERO, Q64::MAX)), vec![0, 1]);
+    assert!(far.within_radius(a, -Q64::ONE).is_empty() && far_tree.within_radius(a, -Q64::ONE).is_empty());
+
+    grid.clear();
+    assert!(grid.is_empty() && grid.nearest(Q64Vec::ZERO, 3).is_empty());
+    assert!(KdTree::<()>::new(vec![]).nearest(Q64Vec::ZERO, 3).is_empty());
+  }
+
+  #[test]
+  fn test_checksum() {
+    use crate::checksum::{checksum, StableHash, StableHasher};
+
+    // The published FNV-1a test vectors.
+    assert_eq!(StableHasher::new().finish(), 0xcbf29ce484222325);
+    let mut hasher = StableHasher::new();
+    hasher.write(b"a");
+    assert_eq!(hasher.finish(), 0xaf63dc4c8601ec8c);
+    hasher.write(b"bc");
+    let mut whole = StableHasher::new();
+    whole.write(b"abc");
+    assert_eq!(hasher.finish(), whole.finish());
+
+    // Pinned, so that the encodings can't change without bumping the version.
+    assert_eq!(StableHasher::VERSION, 1);
+    assert_eq!(checksum(&Q64::ONE), 0x08cd4c29d1e47d34);
+    assert_eq!(checksum(&Q64Vec::from_i32(3, -4)), 0xc5362dd2f8218fe1);
+    assert_eq!(checksum(&Q64Rng::new(0)), 0xdf305f2f2e2d465f);
+
+    // Fixed-point values hash as their raw bits, and vectors as their coordinates.
+    assert_eq!(checksum(&Q64::ONE), checksum(&(1i64 << 32)));
+    assert_eq!(checksum(&Q32::ONE), checksum(&(1i32 << 16)));
+    let v = Q64Vec3::new(Q64::ONE, Q64::HALF, -Q64::ONE);
+    assert_eq!(checksum(&v), checksum(&(v.x, v.y, v.z)));
+    assert_ne!(checksum(&Q64Vec::new(Q64::ONE, Q64::ZERO)), checksum(&Q64Vec::new(Q64::ZERO, Q64::ONE)));
+
+    // Lengths and tags keep neighboring values apart.
+    assert_ne!(checksum(&("ab", "c")), checksum(&("a", "bc")));
+    assert_ne!(checksum(&(vec![1u8], vec![2u8])), checksum(&(vec![1u8, 2], Vec::<u8>::new())));
+    assert_ne!(checksum(&Some(0u8)), checksum(&None::<u8>));
+    assert_eq!(checksum(&5usize), checksum(&5u64));
+    assert_eq!(checksum(&[1u16, 2]), checksum(&(1u16, 2u16)));
+
+    let mut rng = Q64Rng::new(3);
+    let before = checksum(&rng);
+    rng.next_u64();
+    assert_ne!(checksum(&rng), before);
+
+    struct Unit {
+      position: Q64Vec,
+      tag: &'static str,
+    }
+    impl StableHash for Unit {
+      fn stable_hash(&self, hasher: &mut StableHasher) {
+        self.position.stable_hash(hasher);
+        self.tag.stable_hash(hasher);
+      }
+    }
+    let units = [Unit { position: Q64Vec::from_i32(1, 2), tag: "a" }, Unit { position: Q64Vec::ZERO, tag: "b" }];
+    let mut hasher = StableHasher::new();
+    hasher.update(&units[..]);
+    let mut manual = StableHasher::new();
+    manual.update(&2u64);
+    for unit in &units {
+      manual.update(&unit.position);
+      manual.update(unit.tag);
+    }
+    assert_eq!(hasher.finish(), manual.finish());
+  }
+
+  #[test]
+  fn test_const_construction() {
+    const TUNED: Q64 = q64!("0.596227");
+    const NEGATIVE: Q64 = q64!(-1.5);
+    const SCIENTIFIC: Q64 = q64!(2.5e-3);
+    const INTEGER: Q64 = q64!(3);
+    const TENTH: Q32 = Q32::from_str_const("0.1");
+    const SPAWN: Q64Vec = Q64Vec::new(q64!("12.25"), q64!(-0.75));
+    const UP: Q64Vec3 = Q64Vec3::from_i32(0, 0, 1);
+    const HALF_F64: Q64Vec = Q64Vec::from_f64(0.5, -0.5);
+    assert_eq!(TUNED, Q64::from_bits(2560775466));
+    assert_eq!(NEGATIVE, Q64::from_f64(-1.5));
+    assert_eq!(SCIENTIFIC, "0.0025".parse().unwrap());
+    assert_eq!(INTEGER, Q64::from_i32(3));
+    assert_eq!(TENTH, "0.1".parse().unwrap());
+    assert_eq!(SPAWN, Q64Vec::from_f64(12.25, -0.75));
+    assert_eq!(UP.z, Q64::ONE);
+    assert_eq!(HALF_F64, Q64Vec::new(Q64::HALF, -Q64::HALF));
+
+    // The const arithmetic rounds exactly like the operators.
+    const AREA: Q64 = q64!("2.5").const_mul(q64!("-1.3"));
+    const THIRD: Q64 = Q64::ONE.const_div(Q64::from_i32(3));
+    const SUM: Q64 = q64!(0.25).const_add(Q64::ONE).const_sub(q64!(2)).const_neg();
+    assert_eq!(AREA, q64!("2.5") * q64!("-1.3"));
+    assert_eq!(THIRD, Q64::ONE / Q64::from_i32(3));
+    assert_eq!(SUM, q64!(0.75));
+    let mut rng = Q64Rng::new(11);
+    for _ in 0..1000 {
+      let (a, b) = (Q64::from_bits(rng.next_u64() as i64 >> 20), Q64::from_bits(rng.next_u64() as i64 >> 24));
+      assert_eq!(a.const_mul(b), a * b);
+      assert_eq!(a.const_div(b), a / b);
+      let (a, b) = (Q32::from_bits(a.0 as i32 >> 8), Q32::from_bits(b.0 as i32 >> 12));
+      assert_eq!(a.const_mul(b), a * b);
+      assert_eq!(a.const_div(b), a / b);
+      let (a, b) = (Fixed::<i128, 64>(a.0 as i128 * 987654321), Fixed::<i128, 64>(b.0 as i128 * 123456789));
+      assert_eq!(a.const_mul(b), a * b);
+      assert_eq!(a.const_div(b), a / b);
+    }
+
+    // The const parser still keeps only the first 170 digits, and the dropped ones still break ties.
+    let tie = "0.000000000116415321826934814453125";
+    assert_eq!(Q64::from_str_const(tie), Q64::ZERO);
+    let above_tie = format!("{}{}1", tie, "0".repeat(200));
+    assert_eq!(Q64::from_str_const(&above_tie), Q64::EPSILON);
+    assert_eq!(format!("1{}", "0".repeat(300)).parse::<Q64>(), Err(ParseFixedError::Overflow));
+    assert_eq!(format!("0.{}1e300", "0".repeat(300)).parse::<Q64>(), "0.1".parse());
+  }
+
+  #[test]
+  #[should_panic(expected = "invalid fixed-point literal")]
+  fn test_from_str_const_invalid() {
+    Q64::from_str_const("1.2.3");
+  }
+
+  #[test]
+  fn test_math_constants() {
+    use std::f64::consts;
+    // Each constant must be within half an ulp of the true value, which f64 resolves for these
+    // layouts. The fractional parts are all at least 0.01 ulp from a tie.
+    fn assert_nearest<const FRAC: u32>(constant: i128, value: f64) {
+      let scaled = value * (1u64 << FRAC) as f64;
+      assert!((constant as f64 - scaled).abs() < 0.49, "{} is not the nearest to {}", constant, scaled);
+    }
+    let expected = [
+      (Q64::E, consts::E),
+      (Q64::LN_2, consts::LN_2),
+      (Q64::LN_10, consts::LN_10),
+      (Q64::SQRT_2, consts::SQRT_2),
+      (Q64::FRAC_1_SQRT_2, consts::FRAC_1_SQRT_2),
+      (Q64::TAU, consts::TAU),
+      (Q64::PI_ROUNDED, consts::PI),
+      (Q64::FRAC_PI_2, consts::FRAC_PI_2),
+      (Q64::FRAC_PI_4, consts::FRAC_PI_4),
+      (Q64::DEG_TO_RAD, consts::PI / 180.0),
+      (Q64::RAD_TO_DEG, 180.0 / consts::PI),
+    ];
+    for (constant, value) in expected {
+      assert_nearest::<32>(constant.0 as i128, value);
+    }
+    let expected = [
+      (Q32::E, consts::E),
+      (Q32::LN_2, consts::LN_2),
+      (Q32::LN_10, consts::LN_10),
+      (Q32::SQRT_2, consts::SQRT_2),
+      (Q32::FRAC_1_SQRT_2, consts::FRAC_1_SQRT_2),
+      (Q32::TAU, consts::TAU),
+      (Q32::PI_ROUNDED, consts::PI),
+      (Q32::FRAC_PI_2, consts::FRAC_PI_2),
+      (Q32::FRAC_PI_4, consts::FRAC_PI_4),
+      (Q32::DEG_TO_RAD, consts::PI / 180.0),
+      (Q32::RAD_TO_DEG, 180.0 / consts::PI),
+    ];
+    for (constant, value) in expected {
+      assert_nearest::<16>(constant.0 as i128, value);
+    }
+    // Too many bits for f64, so these are checked against values computed to 600 bits.
+    type Q128 = Fixed<i128, 64>;
+    assert_eq!(Q128::E.0, 50143449209799256683);
+    assert_eq!(Q128::LN_2.0, 12786308645202655660);
+    assert_eq!(Q128::LN_10.0, 42475197918399869020);
+    assert_eq!(Q128::SQRT_2.0, 26087635650665564425);
+    assert_eq!(Q128::FRAC_1_SQRT_2.0, 13043817825332782212);
+    assert_eq!(Q128::TAU.0, 115904311329233965478);
+    assert_eq!(Q128::PI_ROUNDED.0, 57952155664616982739);
+    assert_eq!(Q128::FRAC_PI_2.0, 28976077832308491370);
+    assert_eq!(Q128::FRAC_PI_4.0, 14488038916154245685);
+    assert_eq!(Q128::DEG_TO_RAD.0, 321956420358983237);
+    assert_eq!(Q128::RAD_TO_DEG.0, 1056920581181520441539);
+    assert_eq!(Q64::TAU, Q64::PI_TIMES_2);
+    assert_eq!(Q64::FRAC_PI_2, Q64::PI_OVER_TWO);
+    assert_eq!(Q64::PI_ROUNDED, Q64::PI + Q64::EPSILON);
+    assert_eq!(Q64::MAX, Q64::from_bits(i64::MAX));
+    assert_eq!(Q64::MIN, Q64::from_bits(i64::MIN));
+    assert_eq!(Q64::MIN_POSITIVE, Q64::EPSILON);
+    assert_eq!(Q32::MAX.0, i32::MAX);
+
+    // The conversions round the exact product, so they can differ from multiplying by the rounded
+    // constants.
+    assert_eq!(Q64::ONE.to_degrees(), Q64::RAD_TO_DEG);
+    assert_eq!(Q64::ONE.to_radians(), Q64::DEG_TO_RAD);
+    assert_eq!(Q64::from_i32(90).to_radians(), Q64::FRAC_PI_2);
+    assert_eq!(Q64::from_i32(180).to_radians(), Q64::PI_ROUNDED);
+    assert_eq!(Q64::from_i32(360).to_radians(), Q64::TAU);
+    assert_eq!(Q64::PI_OVER_TWO.to_degrees(), Q64::from_bits(386547056625));
+    assert_eq!((-Q64::FRAC_PI_4).to_degrees(), Q64::from_bits(-193273528313));
+    assert_eq!(Q32::from_i32(-45).to_radians(), -Q32::FRAC_PI_4);
+    let mut rng = Q64Rng::new(25);
+    for _ in 0..1000 {
+      let degrees = Q64::from_bits(rng.next_u64() as i64 >> 24);
+      assert!((degrees.to_radians().to_degrees() - degrees).abs() <= Q64::from_bits(32));
+    }
+  }
+
+  #[cfg(feature = "num-traits")]
+  #[test]
+  fn test_num_traits() {
+    use num_traits::{
+      Bounded, CheckedAdd, CheckedDiv, CheckedMul, CheckedRem, FromPrimitive, Num, One, Signed, ToPrimitive, Zero,
+    };
+
+    fn sum_of_squares<T: Num + Copy>(xs: &[T]) -> T {
+      xs.iter().fold(T::zero(), |acc, &x| acc + x * x)
+    }
+    let xs = [Q64::from_i32(3), Q64::from_i32(-4), Q64::HALF];
+    assert_eq!(sum_of_squares(&xs), Q64::from_f64(25.25));
+
+    assert!(Q64::zero().is_zero());
+    assert_eq!(Q64::one(), Q64::ONE);
+    assert_eq!(Q64::from_str_radix("-12.375", 10), Ok(Q64::from_f64(-12.375)));
+    assert_eq!(Q64::from_str_radix("ff", 16), Err(ParseFixedError::Invalid));
+
+    assert_eq!(Signed::abs(&-Q64::HALF), Q64::HALF);
+    assert_eq!(Signed::abs_sub(&Q64::ONE, &Q64::HALF), Q64::HALF);
+    assert_eq!(Signed::abs_sub(&Q64::HALF, &Q64::ONE), Q64::ZERO);
+    assert_eq!(Signed::signum(&-Q64::from_i32(7)), -Q64::ONE);
+    assert!(Signed::is_negative(&-Q64::EPSILON) && !Signed::is_positive(&Q64::ZERO));
+
+    assert_eq!(Q64::max_value(), Q64::from_bits(i64::MAX));
+    assert_eq!(Q64::min_value(), Q64::from_bits(i64::MIN));
+    assert_eq!(CheckedAdd::checked_add(&Q64::max_value(), &Q64::EPSILON), None);
+    assert_eq!(CheckedMul::checked_mul(&Q64::from_i32(1 << 16), &Q64::from_i32(1 << 15)), None);
+    assert_eq!(CheckedDiv::checked_div(&Q64::ONE, &Q64::ZERO), None);
+    assert_eq!(CheckedRem::checked_rem(&Q64::ONE, &Q64::ZERO), None);
+    assert_eq!(CheckedRem::checked_rem(&Q64::from_i32(5), &Q64::from_i32(3)), Some(Q64::from_i32(2)));
+
+    assert_eq!(Q64::from_i64(-(1 << 31)), Some(Q64::from_i32(i32::MIN)));
+    assert_eq!(Q64::from_i64(1 << 31), None);
+    assert_eq!(Q64::from_u64(u64::MAX), None);
+    assert_eq!(<Q64 as FromPrimitive>::from_f64(0.75), Some(Q64::from_f64(0.75)));
+    assert_eq!(<Q64 as FromPrimitive>::from_f64(f64::NAN), None);
+    assert_eq!(<Q64 as FromPrimitive>::from_f64(1e10), None);
+    assert_eq!(Q32::from_i64(1 << 15), None);
+
+    assert_eq!(Q64::from_f64(-2.75).to_i64(), Some(-2));
+    assert_eq!(Q64::from_f64(2.75).to_i64(), Some(2));
+    assert_eq!(Q64::from_f64(-2.75).to_u64(), None);
+    assert_eq!(Q64::from_f64(-0.75).to_u64(), Some(0));
+    assert_eq!(ToPrimitive::to_i32(&Q64::max_value()), Some(i32::MAX));
+    assert_eq!(ToPrimitive::to_f64(&Q64::from_f64(-2.75)), Some(-2.75));
+  }
 }
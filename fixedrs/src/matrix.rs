@@ -0,0 +1,306 @@
+// ==================== Matrices and affine transforms ====================
+
+// Matrices are stored as columns, so that the columns are the images of the basis vectors and
+// `a * b` is the transform that applies `b` first and then `a`.
+
+use crate::{FixedError, Q64Vec, Q64Vec3, Q64};
+
+fn checked_det2(a: Q64, b: Q64, c: Q64, d: Q64) -> Result<Q64, FixedError> {
+  let ad = a.checked_mul(d).ok_or(FixedError::Overflow)?;
+  let bc = b.checked_mul(c).ok_or(FixedError::Overflow)?;
+  ad.checked_sub(bc).ok_or(FixedError::Overflow)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Q64Mat2 {
+  pub x_axis: Q64Vec,
+  pub y_axis: Q64Vec,
+}
+
+impl Default for Q64Mat2 {
+  fn default() -> Self {
+    Self::IDENTITY
+  }
+}
+
+impl Q64Mat2 {
+  pub const ZERO: Self = Self {
+    x_axis: Q64Vec::ZERO,
+    y_axis: Q64Vec::ZERO,
+  };
+
+  pub const IDENTITY: Self = Self {
+    x_axis: Q64Vec { x: Q64::ONE, y: Q64::ZERO },
+    y_axis: Q64Vec { x: Q64::ZERO, y: Q64::ONE },
+  };
+
+  pub fn from_cols(x_axis: Q64Vec, y_axis: Q64Vec) -> Self {
+    Self { x_axis, y_axis }
+  }
+
+  /// The counterclockwise rotation by `angle` radians.
+  pub fn from_angle(angle: Q64) -> Self {
+    let (sin, cos) = angle.sin_cos();
+    Self::from_cols(Q64Vec::new(cos, sin), Q64Vec::new(-sin, cos))
+  }
+
+  pub fn from_scale(scale: Q64Vec) -> Self {
+    Self::from_cols(Q64Vec::new(scale.x, Q64::ZERO), Q64Vec::new(Q64::ZERO, scale.y))
+  }
+
+  pub fn transpose(self) -> Self {
+    Self::from_cols(
+      Q64Vec::new(self.x_axis.x, self.y_axis.x),
+      Q64Vec::new(self.x_axis.y, self.y_axis.y),
+    )
+  }
+
+  pub fn determinant(self) -> Q64 {
+    self.x_axis.cross(self.y_axis)
+  }
+
+  /// Fails with `Singular` if the determinant is zero, and with `Overflow` if the inverse doesn't
+  /// fit, which can happen when the determinant is tiny.
+  pub fn try_inverse(self) -> Result<Self, FixedError> {
+    let (a, b, c, d) = (self.x_axis.x, self.y_axis.x, self.x_axis.y, self.y_axis.y);
+    let det = checked_det2(a, b, c, d)?;
+    if det == Q64::ZERO {
+      return Err(FixedError::Singular);
+    }
+    let neg = |x: Q64| x.checked_neg().ok_or(FixedError::Overflow);
+    Ok(Self::from_cols(
+      Q64Vec::new(d.try_div(det)?, neg(c)?.try_div(det)?),
+      Q64Vec::new(neg(b)?.try_div(det)?, a.try_div(det)?),
+    ))
+  }
+
+  pub fn transform_vector(self, v: Q64Vec) -> Q64Vec {
+    self.x_axis * v.x + self.y_axis * v.y
+  }
+}
+
+impl std::ops::Mul for Q64Mat2 {
+  type Output = Self;
+  fn mul(self, rhs: Self) -> Self {
+    Self::from_cols(self.transform_vector(rhs.x_axis), self.transform_vector(rhs.y_axis))
+  }
+}
+
+impl std::ops::Mul<Q64Vec> for Q64Mat2 {
+  type Output = Q64Vec;
+  fn mul(self, rhs: Q64Vec) -> Q64Vec {
+    self.transform_vector(rhs)
+  }
+}
+
+impl std::ops::MulAssign for Q64Mat2 {
+  fn mul_assign(&mut self, rhs: Self) {
+    *self = *self * rhs;
+  }
+}
+
+/// A 3x3 matrix, which acts on `Q64Vec3`, and on `Q64Vec` as a 2D transform in homogeneous
+/// coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Q64Mat3 {
+  pub x_axis: Q64Vec3,
+  pub y_axis: Q64Vec3,
+  pub z_axis: Q64Vec3,
+}
+
+impl Default for Q64Mat3 {
+  fn default() -> Self {
+    Self::IDENTITY
+  }
+}
+
+impl Q64Mat3 {
+  pub const ZERO: Self = Self {
+    x_axis: Q64Vec3::ZERO,
+    y_axis: Q64Vec3::ZERO,
+    z_axis: Q64Vec3::ZERO,
+  };
+
+  pub const IDENTITY: Self = Self {
+    x_axis: Q64Vec3 { x: Q64::ONE, y: Q64::ZERO, z: Q64::ZERO },
+    y_axis: Q64Vec3 { x: Q64::ZERO, y: Q64::ONE, z: Q64::ZERO },
+    z_axis: Q64Vec3 { x: Q64::ZERO, y: Q64::ZERO, z: Q64::ONE },
+  };
+
+  pub fn from_cols(x_axis: Q64Vec3, y_axis: Q64Vec3, z_axis: Q64Vec3) -> Self {
+    Self { x_axis, y_axis, z_axis }
+  }
+
+  pub fn transpose(self) -> Self {
+    Self::from_cols(
+      Q64Vec3::new(self.x_axis.x, self.y_axis.x, self.z_axis.x),
+      Q64Vec3::new(self.x_axis.y, self.y_axis.y, self.z_axis.y),
+      Q64Vec3::new(self.x_axis.z, self.y_axis.z, self.z_axis.z),
+    )
+  }
+
+  pub fn determinant(self) -> Q64 {
+    self.x_axis.dot(self.y_axis.cross(self.z_axis))
+  }
+
+  /// Fails like `Q64Mat2::try_inverse`.
+  pub fn try_inverse(self) -> Result<Self, FixedError> {
+    let m = [self.x_axis, self.y_axis, self.z_axis].map(|col| [col.x, col.y, col.z]);
+    // cofactor(i, j) for column i and row j, from the 2x2 minor with the other columns and rows.
+    let cofactor = |i: usize, j: usize| {
+      let (i1, i2) = ((i + 1) % 3, (i + 2) % 3);
+      let (j1, j2) = ((j + 1) % 3, (j + 2) % 3);
+      checked_det2(m[i1][j1], m[i2][j1], m[i1][j2], m[i2][j2])
+    };
+    let mut cofactors = [[Q64::ZERO; 3]; 3];
+    for (i, column) in cofactors.iter_mut().enumerate() {
+      for (j, entry) in column.iter_mut().enumerate() {
+        *entry = cofactor(i, j)?;
+      }
+    }
+    // Expanding along the first column, which rounds exactly like `determinant`.
+    let mut det = Q64::ZERO;
+    for (&entry, &cofactor) in m[0].iter().zip(&cofactors[0]) {
+      let term = entry.checked_mul(cofactor).ok_or(FixedError::Overflow)?;
+      det = det.checked_add(term).ok_or(FixedError::Overflow)?;
+    }
+    if det == Q64::ZERO {
+      return Err(FixedError::Singular);
+    }
+    // The inverse is the transpose of the cofactor matrix, divided by the determinant.
+    let mut inverse = [[Q64::ZERO; 3]; 3];
+    for (i, column) in inverse.iter_mut().enumerate() {
+      for (j, entry) in column.iter_mut().enumerate() {
+        *entry = cofactors[j][i].try_div(det)?;
+      }
+    }
+    let [x, y, z] = inverse.map(|[a, b, c]| Q64Vec3::new(a, b, c));
+    Ok(Self::from_cols(x, y, z))
+  }
+
+  pub fn transform_vector3(self, v: Q64Vec3) -> Q64Vec3 {
+    self.x_axis * v.x + self.y_axis * v.y + self.z_axis * v.z
+  }
+
+  /// Transforms a 2D point, as `(x, y, 1)`, dividing through by the resulting homogeneous
+  /// coordinate. Fails with `DivideByZero` for points that map to infinity.
+  pub fn try_transform_point(self, p: Q64Vec) -> Result<Q64Vec, FixedError> {
+    let h = self.transform_vector3(Q64Vec3::new(p.x, p.y, Q64::ONE));
+    match h.z {
+      Q64::ONE => Ok(Q64Vec::new(h.x, h.y)),
+      w => Ok(Q64Vec::new(h.x.try_div(w)?, h.y.try_div(w)?)),
+    }
+  }
+
+  /// Transforms a 2D direction, as `(x, y, 0)`, ignoring the homogeneous coordinate.
+  pub fn transform_vector(self, v: Q64Vec) -> Q64Vec {
+    let h = self.transform_vector3(Q64Vec3::new(v.x, v.y, Q64::ZERO));
+    Q64Vec::new(h.x, h.y)
+  }
+}
+
+impl std::ops::Mul for Q64Mat3 {
+  type Output = Self;
+  fn mul(self, rhs: Self) -> Self {
+    Self::from_cols(
+      self.transform_vector3(rhs.x_axis),
+      self.transform_vector3(rhs.y_axis),
+      self.transform_vector3(rhs.z_axis),
+    )
+  }
+}
+
+impl std::ops::Mul<Q64Vec3> for Q64Mat3 {
+  type Output = Q64Vec3;
+  fn mul(self, rhs: Q64Vec3) -> Q64Vec3 {
+    self.transform_vector3(rhs)
+  }
+}
+
+impl std::ops::MulAssign for Q64Mat3 {
+  fn mul_assign(&mut self, rhs: Self) {
+    *self = *self * rhs;
+  }
+}
+
+/// A 2D affine transform: the linear part `matrix` followed by a `translation`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Q64Affine2 {
+  pub matrix: Q64Mat2,
+  pub translation: Q64Vec,
+}
+
+impl Default for Q64Affine2 {
+  fn default() -> Self {
+    Self::IDENTITY
+  }
+}
+
+impl Q64Affine2 {
+  pub const IDENTITY: Self = Self {
+    matrix: Q64Mat2::IDENTITY,
+    translation: Q64Vec::ZERO,
+  };
+
+  pub fn new(matrix: Q64Mat2, translation: Q64Vec) -> Self {
+    Self { matrix, translation }
+  }
+
+  pub fn from_translation(translation: Q64Vec) -> Self {
+    Self::new(Q64Mat2::IDENTITY, translation)
+  }
+
+  pub fn from_angle(angle: Q64) -> Self {
+    Self::new(Q64Mat2::from_angle(angle), Q64Vec::ZERO)
+  }
+
+  pub fn from_scale(scale: Q64Vec) -> Self {
+    Self::new(Q64Mat2::from_scale(scale), Q64Vec::ZERO)
+  }
+
+  /// Scales, then rotates counterclockwise by `angle`, then translates.
+  pub fn from_scale_angle_translation(scale: Q64Vec, angle: Q64, translation: Q64Vec) -> Self {
+    Self::new(Q64Mat2::from_angle(angle) * Q64Mat2::from_scale(scale), translation)
+  }
+
+  pub fn determinant(self) -> Q64 {
+    self.matrix.determinant()
+  }
+
+  /// Fails like `Q64Mat2::try_inverse`.
+  pub fn try_inverse(self) -> Result<Self, FixedError> {
+    let matrix = self.matrix.try_inverse()?;
+    Ok(Self::new(matrix, -(matrix * self.translation)))
+  }
+
+  pub fn transform_point(self, p: Q64Vec) -> Q64Vec {
+    self.matrix * p + self.translation
+  }
+
+  /// Applies only the linear part, as is right for directions and offsets.
+  pub fn transform_vector(self, v: Q64Vec) -> Q64Vec {
+    self.matrix * v
+  }
+
+  pub fn to_mat3(self) -> Q64Mat3 {
+    let column = |v: Q64Vec, z: Q64| Q64Vec3::new(v.x, v.y, z);
+    Q64Mat3::from_cols(
+      column(self.matrix.x_axis, Q64::ZERO),
+      column(self.matrix.y_axis, Q64::ZERO),
+      column(self.translation, Q64::ONE),
+    )
+  }
+}
+
+impl std::ops::Mul for Q64Affine2 {
+  type Output = Self;
+  fn mul(self, rhs: Self) -> Self {
+    Self::new(self.matrix * rhs.matrix, self.transform_point(rhs.translation))
+  }
+}
+
+impl std::ops::MulAssign for Q64Affine2 {
+  fn mul_assign(&mut self, rhs: Self) {
+    *self = *self * rhs;
+  }
+}
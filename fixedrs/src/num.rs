@@ -0,0 +1,224 @@
+// ==================== num-traits ====================
+
+// Implementations of the num-traits traits, behind the `num-traits` feature, so that fixed-point
+// values work with generic numeric code. They forward to the inherent methods of the same names.
+
+use crate::{Fixed, ParseFixedError};
+use ::num_traits::{
+  Bounded, CheckedAdd, CheckedDiv, CheckedMul, CheckedNeg, CheckedRem, CheckedSub, FromPrimitive, Num, One,
+  SaturatingAdd, SaturatingMul, SaturatingSub, Signed, ToPrimitive, WrappingAdd, WrappingMul, WrappingNeg,
+  WrappingSub, Zero,
+};
+
+macro_rules! impl_num_traits {
+  ($repr:ty) => {
+    impl<const FRAC: u32> Fixed<$repr, FRAC> {
+      /// The integer part, truncated toward zero like the f64 conversions in `ToPrimitive`.
+      fn trunc_to_i128(self) -> i128 {
+        (self.0 / ((1 as $repr) << FRAC)) as i128
+      }
+
+      fn checked_from_i128(n: i128) -> Option<Self> {
+        let (min, max) = ((<$repr>::MIN >> FRAC) as i128, (<$repr>::MAX >> FRAC) as i128);
+        match (min..=max).contains(&n) {
+          true => Some(Self((n as $repr) << FRAC)),
+          false => None,
+        }
+      }
+    }
+
+    impl<const FRAC: u32> Zero for Fixed<$repr, FRAC> {
+      fn zero() -> Self {
+        Self::ZERO
+      }
+
+      fn is_zero(&self) -> bool {
+        self.0 == 0
+      }
+    }
+
+    impl<const FRAC: u32> One for Fixed<$repr, FRAC> {
+      fn one() -> Self {
+        Self::ONE
+      }
+    }
+
+    /// Only radix 10 is supported, which parses like `FromStr`; any other radix fails with
+    /// `Invalid`.
+    impl<const FRAC: u32> Num for Fixed<$repr, FRAC> {
+      type FromStrRadixErr = ParseFixedError;
+      fn from_str_radix(s: &str, radix: u32) -> Result<Self, ParseFixedError> {
+        match radix {
+          10 => s.parse(),
+          _ => Err(ParseFixedError::Invalid),
+        }
+      }
+    }
+
+    impl<const FRAC: u32> Signed for Fixed<$repr, FRAC> {
+      fn abs(&self) -> Self {
+        Self::abs(*self)
+      }
+
+      /// The positive difference, `max(self - other, 0)`.
+      fn abs_sub(&self, other: &Self) -> Self {
+        match *self > *other {
+          true => *self - *other,
+          false => Self::ZERO,
+        }
+      }
+
+      fn signum(&self) -> Self {
+        Self::signum(*self)
+      }
+
+      fn is_positive(&self) -> bool {
+        Self::is_positive(*self)
+      }
+
+      fn is_negative(&self) -> bool {
+        Self::is_negative(*self)
+      }
+    }
+
+    impl<const FRAC: u32> Bounded for Fixed<$repr, FRAC> {
+      fn min_value() -> Self {
+        Self::MIN
+      }
+
+      fn max_value() -> Self {
+        Self::MAX
+      }
+    }
+
+    impl<const FRAC: u32> CheckedAdd for Fixed<$repr, FRAC> {
+      fn checked_add(&self, v: &Self) -> Option<Self> {
+        Self::checked_add(*self, *v)
+      }
+    }
+
+    impl<const FRAC: u32> CheckedSub for Fixed<$repr, FRAC> {
+      fn checked_sub(&self, v: &Self) -> Option<Self> {
+        Self::checked_sub(*self, *v)
+      }
+    }
+
+    impl<const FRAC: u32> CheckedMul for Fixed<$repr, FRAC> {
+      fn checked_mul(&self, v: &Self) -> Option<Self> {
+        Self::checked_mul(*self, *v)
+      }
+    }
+
+    impl<const FRAC: u32> CheckedDiv for Fixed<$repr, FRAC> {
+      fn checked_div(&self, v: &Self) -> Option<Self> {
+        Self::checked_div(*self, *v)
+      }
+    }
+
+    impl<const FRAC: u32> CheckedRem for Fixed<$repr, FRAC> {
+      fn checked_rem(&self, v: &Self) -> Option<Self> {
+        self.0.checked_rem(v.0).map(Self)
+      }
+    }
+
+    impl<const FRAC: u32> CheckedNeg for Fixed<$repr, FRAC> {
+      fn checked_neg(&self) -> Option<Self> {
+        Self::checked_neg(*self)
+      }
+    }
+
+    impl<const FRAC: u32> SaturatingAdd for Fixed<$repr, FRAC> {
+      fn saturating_add(&self, v: &Self) -> Self {
+        Self::saturating_add(*self, *v)
+      }
+    }
+
+    impl<const FRAC: u32> SaturatingSub for Fixed<$repr, FRAC> {
+      fn saturating_sub(&self, v: &Self) -> Self {
+        Self::saturating_sub(*self, *v)
+      }
+    }
+
+    impl<const FRAC: u32> SaturatingMul for Fixed<$repr, FRAC> {
+      fn saturating_mul(&self, v: &Self) -> Self {
+        Self::saturating_mul(*self, *v)
+      }
+    }
+
+    impl<const FRAC: u32> WrappingAdd for Fixed<$repr, FRAC> {
+      fn wrapping_add(&self, v: &Self) -> Self {
+        Self::wrapping_add(*self, *v)
+      }
+    }
+
+    impl<const FRAC: u32> WrappingSub for Fixed<$repr, FRAC> {
+      fn wrapping_sub(&self, v: &Self) -> Self {
+        Self::wrapping_sub(*self, *v)
+      }
+    }
+
+    impl<const FRAC: u32> WrappingMul for Fixed<$repr, FRAC> {
+      fn wrapping_mul(&self, v: &Self) -> Self {
+        Self::wrapping_mul(*self, *v)
+      }
+    }
+
+    impl<const FRAC: u32> WrappingNeg for Fixed<$repr, FRAC> {
+      fn wrapping_neg(&self) -> Self {
+        Self(self.0.wrapping_neg())
+      }
+    }
+
+    /// Integers convert exactly or not at all. Floats round to nearest with ties to even, like
+    /// `try_from_f64`, and fail if out of range or not finite.
+    impl<const FRAC: u32> FromPrimitive for Fixed<$repr, FRAC> {
+      fn from_i64(n: i64) -> Option<Self> {
+        Self::checked_from_i128(n as i128)
+      }
+
+      fn from_u64(n: u64) -> Option<Self> {
+        Self::checked_from_i128(n as i128)
+      }
+
+      fn from_i128(n: i128) -> Option<Self> {
+        Self::checked_from_i128(n)
+      }
+
+      fn from_u128(n: u128) -> Option<Self> {
+        Self::checked_from_i128(i128::try_from(n).ok()?)
+      }
+
+      fn from_f64(n: f64) -> Option<Self> {
+        Self::try_from_f64(n).ok()
+      }
+    }
+
+    /// Conversions to integers truncate toward zero and fail if the result is out of range, like
+    /// the ones for f64. Conversion to f64 is `to_f64`, which rounds to nearest.
+    impl<const FRAC: u32> ToPrimitive for Fixed<$repr, FRAC> {
+      fn to_i64(&self) -> Option<i64> {
+        self.trunc_to_i128().try_into().ok()
+      }
+
+      fn to_u64(&self) -> Option<u64> {
+        self.trunc_to_i128().try_into().ok()
+      }
+
+      fn to_i128(&self) -> Option<i128> {
+        Some(self.trunc_to_i128())
+      }
+
+      fn to_u128(&self) -> Option<u128> {
+        self.trunc_to_i128().try_into().ok()
+      }
+
+      fn to_f64(&self) -> Option<f64> {
+        Some(Self::to_f64(*self))
+      }
+    }
+  };
+}
+
+impl_num_traits!(i32);
+impl_num_traits!(i64);
+impl_num_traits!(i128);
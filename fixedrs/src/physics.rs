@@ -0,0 +1,569 @@
+// ==================== Rigid-body physics ====================
+
+//! Rigid-body physics in the plane, built on `geometry`, for lockstep simulations.
+//!
+//! A `World` advances in fixed steps. Each step applies gravity to the velocities, finds contacts
+//! with a sort-and-sweep broad phase, resolves them with sequential impulses, moves the bodies, and
+//! finally pushes apart bodies that still overlap. Everything is integer arithmetic in a fixed
+//! order, so the same world stepped the same way ends in bit-identical state on every platform.
+
+use crate::geometry::{Aabb, Circle, Polygon};
+use crate::{FixedError, Q64Affine2, Q64Mat2, Q64Vec, Q64, Q64_SHIFT};
+
+/// Collisions closing slower than this, in units per second, don't bounce, so that resting
+/// contacts settle instead of jittering.
+const RESTITUTION_THRESHOLD: Q64 = Q64::ONE;
+
+/// Overlap that the position correction leaves alone, so that resting contacts stay in contact.
+const SLOP: Q64 = Q64::from_bits(1 << 25);
+
+/// The fraction of the remaining overlap that the position correction removes each step.
+const CORRECTION_FRACTION: Q64 = Q64::from_bits(3 << 30);
+
+/// The panic message for a body that fails `Body::validate`.
+const INVALID_BODY: &str = "body mass and friction must not be negative";
+
+/// The shape of a body, relative to its position.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Collider {
+  Circle { radius: Q64 },
+  /// A box that stays axis-aligned whatever the body's angle, so collisions never spin the body.
+  Aabb { half_extents: Q64Vec },
+  /// A convex polygon in body coordinates, which rotates with the body. The body rotates about its
+  /// position, so the polygon's centroid should be at the origin.
+  Polygon(Polygon),
+}
+
+/// The collider placed in the world.
+enum Shape {
+  Circle(Circle),
+  Polygon(Polygon),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Body {
+  pub position: Q64Vec,
+  pub velocity: Q64Vec,
+  /// Counterclockwise, in radians. Steps keep it in [0, 2π).
+  pub angle: Q64,
+  pub angular_velocity: Q64,
+  /// Zero makes the body static: gravity and collisions don't affect it, though it still moves at
+  /// its own velocity. Must not be negative, which `World` checks.
+  pub mass: Q64,
+  /// The fraction of the closing speed that a collision reverses, from `ZERO` for no bounce to
+  /// `ONE` for a perfectly elastic one. A pair of bodies uses the larger of the two.
+  pub restitution: Q64,
+  /// The Coulomb friction coefficient. A pair of bodies uses the geometric mean of the two. Must not
+  /// be negative, which `World` checks.
+  pub friction: Q64,
+  pub collider: Collider,
+}
+
+impl Body {
+  /// A body at rest at the origin, with no restitution or friction.
+  pub fn new(collider: Collider, mass: Q64) -> Self {
+    Self {
+      position: Q64Vec::ZERO,
+      velocity: Q64Vec::ZERO,
+      angle: Q64::ZERO,
+      angular_velocity: Q64::ZERO,
+      mass,
+      restitution: Q64::ZERO,
+      friction: Q64::ZERO,
+      collider,
+    }
+  }
+
+  pub fn new_static(collider: Collider) -> Self {
+    Self::new(collider, Q64::ZERO)
+  }
+
+  pub fn is_static(&self) -> bool {
+    self.mass == Q64::ZERO
+  }
+
+  /// `OutOfDomain` if the mass or friction is negative.
+  fn validate(&self) -> Result<(), FixedError> {
+    match self.mass < Q64::ZERO || self.friction < Q64::ZERO {
+      true => Err(FixedError::OutOfDomain),
+      false => Ok(()),
+    }
+  }
+
+  /// The map from body coordinates to world coordinates.
+  pub fn transform(&self) -> Q64Affine2 {
+    Q64Affine2::new(Q64Mat2::from_angle(self.angle), self.position)
+  }
+
+  pub fn aabb(&self) -> Aabb {
+    match self.shape() {
+      Shape::Circle(circle) => circle.aabb(),
+      Shape::Polygon(polygon) => polygon.aabb(),
+    }
+  }
+
+  fn shape(&self) -> Shape {
+    match &self.collider {
+      Collider::Circle { radius } => Shape::Circle(Circle::new(self.position, *radius)),
+      Collider::Aabb { half_extents } => {
+        Shape::Polygon(Aabb::from_center_half_extents(self.position, *half_extents).to_polygon())
+      }
+      Collider::Polygon(polygon) => Shape::Polygon(polygon.rigidly_transformed(&self.transform())),
+    }
+  }
+
+  /// Saturates for tiny masses, whose inverse doesn't fit.
+  fn inverse_mass(&self) -> Q64 {
+    match self.mass {
+      Q64::ZERO => Q64::ZERO,
+      mass => Q64::ONE.saturating_div(mass),
+    }
+  }
+
+  /// The inverse of the moment of inertia about the position, or zero if the body can't spin.
+  /// Saturates for tiny bodies, like `inverse_mass`.
+  fn inverse_inertia(&self) -> Q64 {
+    // The inertia grows with the fourth power of the size before dividing back down to the square,
+    // so shapes wider than 64 are first scaled down by 2^scale, which the inverse then undoes.
+    // Raw values are in i128 to leave room for the mass.
+    let mul = |a: i128, b: i128| (a * b) >> Q64_SHIFT;
+    let (inertia, scale) = match &self.collider {
+      Collider::Circle { radius } => {
+        let scale = scale_exponent(radius.0.unsigned_abs());
+        let r = (radius.0 >> scale) as i128;
+        (mul(mul(mul(self.mass.0 as i128, r), r), Q64::HALF.0 as i128), scale)
+      }
+      Collider::Aabb { .. } => return Q64::ZERO,
+      Collider::Polygon(polygon) => {
+        let extent = polygon.vertices().iter().map(|v| v.x.0.unsigned_abs().max(v.y.0.unsigned_abs())).max();
+        let scale = scale_exponent(extent.unwrap_or(0));
+        // Summing over the triangles that the edges make with the origin.
+        let (mut numerator, mut denominator) = (Q64::ZERO, Q64::ZERO);
+        let shrink = |v: Q64Vec| Q64Vec::new(v.x >> scale, v.y >> scale);
+        for (a, b) in polygon.edges() {
+          let (a, b) = (shrink(a), shrink(b));
+          let cross = a.cross(b);
+          numerator += cross * (a.dot(a) + a.dot(b) + b.dot(b));
+          denominator += cross;
+        }
+        let denominator = denominator.0 as i128 * 6;
+        if denominator == 0 {
+          return Q64::ZERO;
+        }
+        (mul(self.mass.0 as i128, numerator.0 as i128) * Q64::ONE.0 as i128 / denominator, scale)
+      }
+    };
+    if inertia <= 0 {
+      return Q64::ZERO;
+    }
+    let inverse = (((Q64::ONE.0 as i128) << Q64_SHIFT) / inertia) >> (2 * scale);
+    Q64::from_bits(inverse.min(i64::MAX as i128) as i64)
+  }
+
+  /// The velocity of the body-fixed point at offset `r` from the position.
+  fn velocity_at(&self, r: Q64Vec) -> Q64Vec {
+    self.velocity + r.perp() * self.angular_velocity
+  }
+}
+
+/// How far to scale down a shape whose largest raw coordinate is `extent` so that it is at most 64
+/// wide, which keeps its inertia computation in range.
+fn scale_exponent(extent: u64) -> u32 {
+  (u64::BITS - extent.leading_zeros()).saturating_sub(Q64_SHIFT + 6)
+}
+
+/// Identifies a body in a `World`, by the order in which bodies were added.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BodyId(usize);
+
+impl BodyId {
+  pub fn index(self) -> usize {
+    self.0
+  }
+}
+
+/// A point where two bodies touched during a step. Polygons resting edge to edge touch at two
+/// points, so a pair of bodies can have two contacts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Contact {
+  /// The bodies, with `a < b`.
+  pub a: BodyId,
+  pub b: BodyId,
+  pub point: Q64Vec,
+  /// The unit normal, pointing from `a` toward `b`.
+  pub normal: Q64Vec,
+  /// How far the shapes overlap along the normal.
+  pub depth: Q64,
+}
+
+// ==================== Narrow phase ====================
+
+/// How two shapes touch: the unit normal from the first toward the second, and the contact points
+/// with their depths.
+type Manifold = (Q64Vec, Vec<(Q64Vec, Q64)>);
+
+fn collide_circles(a: &Circle, b: &Circle) -> Option<Manifold> {
+  let offset = b.center - a.center;
+  let distance = offset.norm();
+  let depth = a.radius + b.radius - distance;
+  if depth < Q64::ZERO {
+    return None;
+  }
+  let normal = match distance {
+    Q64::ZERO => Q64Vec::new(Q64::ONE, Q64::ZERO),
+    _ => offset.normalized(),
+  };
+  Some((normal, vec![(a.center + normal * (a.radius - depth * Q64::HALF), depth)]))
+}
+
+/// The unit outward normal of a counterclockwise edge, or `None` for an edge of length zero, which
+/// repeated vertices make.
+fn outward_normal(a: Q64Vec, b: Q64Vec) -> Option<Q64Vec> {
+  match -(b - a).perp() {
+    Q64Vec::ZERO => None,
+    normal => Some(normal.normalized()),
+  }
+}
+
+fn collide_polygon_circle(polygon: &Polygon, circle: &Circle) -> Option<Manifold> {
+  if !polygon.contains_point(circle.center) {
+    let closest = polygon.closest_point(circle.center);
+    let offset = circle.center - closest;
+    let depth = circle.radius - offset.norm();
+    return match depth < Q64::ZERO {
+      true => None,
+      false => Some((offset.normalized(), vec![(closest, depth)])),
+    };
+  }
+  // The center is inside, so push it out through the nearest edge. On a tie the earliest edge wins.
+  let mut best: Option<(Q64Vec, Q64)> = None;
+  for (a, b) in polygon.edges() {
+    let Some(normal) = outward_normal(a, b) else { continue };
+    let separation = (circle.center - a).dot(normal);
+    if best.is_none_or(|(_, best_separation)| separation > best_separation) {
+      best = Some((normal, separation));
+    }
+  }
+  let (normal, separation) = best.unwrap();
+  Some((normal, vec![(circle.center - normal * separation, circle.radius - separation)]))
+}
+
+/// Clips a segment to the half-plane `x.dot(direction) >= offset`, or `None` if it is entirely
+/// outside.
+fn clip(segment: (Q64Vec, Q64Vec), direction: Q64Vec, offset: Q64) -> Option<(Q64Vec, Q64Vec)> {
+  let (p, q) = segment;
+  let (dp, dq) = (p.dot(direction) - offset, q.dot(direction) - offset);
+  let crossing = || p + (q - p) * (dp / (dp - dq));
+  match (dp >= Q64::ZERO, dq >= Q64::ZERO) {
+    (true, true) => Some((p, q)),
+    (true, false) => Some((p, crossing())),
+    (false, true) => Some((crossing(), q)),
+    (false, false) => None,
+  }
+}
+
+/// Finds the edge of least penetration by the separating axis theorem, then clips the incident
+/// edge of the other polygon against it, which gives up to two contact points. Two points keep
+/// boxes resting flat from rocking.
+fn collide_polygons(a: &Polygon, b: &Polygon) -> Option<Manifold> {
+  // The reference polygon, the edge, and the depth. On a tie the earliest edge of `a` wins.
+  let mut best: Option<(bool, (Q64Vec, Q64Vec), Q64)> = None;
+  for (reference_is_a, reference, incident) in [(true, a, b), (false, b, a)] {
+    for edge in reference.edges() {
+      let Some(normal) = outward_normal(edge.0, edge.1) else { continue };
+      let depth = edge.0.dot(normal) - incident.project(normal).0;
+      if depth < Q64::ZERO {
+        return None;
+      }
+      if best.is_none_or(|(_, _, best_depth)| depth < best_depth) {
+        best = Some((reference_is_a, edge, depth));
+      }
+    }
+  }
+  let (reference_is_a, (v1, v2), depth) = best?;
+  let incident = if reference_is_a { b } else { a };
+  let normal = outward_normal(v1, v2).unwrap();
+
+  // The incident edge faces the reference edge most directly. On a tie the earliest wins.
+  let mut incident_edge: Option<((Q64Vec, Q64Vec), Q64)> = None;
+  for (p, q) in incident.edges() {
+    let Some(incident_normal) = outward_normal(p, q) else { continue };
+    let alignment = incident_normal.dot(normal);
+    if incident_edge.is_none_or(|(_, best_alignment)| alignment < best_alignment) {
+      incident_edge = Some(((p, q), alignment));
+    }
+  }
+  let tangent = (v2 - v1).normalized();
+  let (edge, _) = incident_edge?;
+  let clipped = clip(edge, tangent, tangent.dot(v1)).and_then(|edge| clip(edge, -tangent, -tangent.dot(v2)));
+
+  let surface = v1.dot(normal);
+  let mut points: Vec<(Q64Vec, Q64)> = match clipped {
+    Some((p, q)) => [p, q].into_iter().map(|x| (x, surface - x.dot(normal))).filter(|&(_, d)| d >= Q64::ZERO).collect(),
+    None => vec![],
+  };
+  if points.is_empty() {
+    // Rounding can clip away every point of a grazing contact, so fall back to the deepest vertex.
+    let mut deepest = incident.vertices()[0];
+    for &v in incident.vertices() {
+      if v.dot(normal) < deepest.dot(normal) {
+        deepest = v;
+      }
+    }
+    points.push((deepest, depth));
+  }
+  let normal = if reference_is_a { normal } else { -normal };
+  Some((normal, points))
+}
+
+fn collide(a: &Shape, b: &Shape) -> Option<Manifold> {
+  match (a, b) {
+    (Shape::Circle(a), Shape::Circle(b)) => collide_circles(a, b),
+    (Shape::Polygon(a), Shape::Circle(b)) => collide_polygon_circle(a, b),
+    (Shape::Circle(a), Shape::Polygon(b)) => collide_polygon_circle(b, a).map(|(normal, points)| (-normal, points)),
+    (Shape::Polygon(a), Shape::Polygon(b)) => collide_polygons(a, b),
+  }
+}
+
+// ==================== Broad phase ====================
+
+/// The pairs of overlapping boxes, as index pairs `(i, j)` with `i < j`, in increasing order. Sorts
+/// the boxes along x and sweeps, so it only compares boxes whose x ranges overlap.
+pub fn overlapping_pairs(aabbs: &[Aabb]) -> Vec<(usize, usize)> {
+  let mut order: Vec<usize> = (0..aabbs.len()).collect();
+  order.sort_by_key(|&i| (aabbs[i].min.x, i));
+  let mut pairs = vec![];
+  for (k, &i) in order.iter().enumerate() {
+    for &j in &order[k + 1..] {
+      if aabbs[j].min.x > aabbs[i].max.x {
+        break;
+      }
+      if aabbs[i].overlaps(&aabbs[j]) {
+        pairs.push((i.min(j), i.max(j)));
+      }
+    }
+  }
+  pairs.sort_unstable();
+  pairs
+}
+
+// ==================== World ====================
+
+/// A contact being resolved, with what the solver needs precomputed.
+struct Constraint {
+  a: usize,
+  b: usize,
+  normal: Q64Vec,
+  /// The contact point relative to each body's position.
+  ra: Q64Vec,
+  rb: Q64Vec,
+  normal_mass: Q64,
+  tangent_mass: Q64,
+  /// The normal speed that the impulses aim for, which is the bounce.
+  target_speed: Q64,
+  friction: Q64,
+  normal_impulse: Q64,
+  tangent_impulse: Q64,
+}
+
+impl Constraint {
+  /// The velocity of `b` relative to `a` at the contact point.
+  fn relative_velocity(&self, bodies: &[Body]) -> Q64Vec {
+    bodies[self.b].velocity_at(self.rb) - bodies[self.a].velocity_at(self.ra)
+  }
+
+  /// Applies `impulse` to `b` at the contact point, and the opposite impulse to `a`.
+  fn apply(&self, bodies: &mut [Body], inverse: &[(Q64, Q64)], impulse: Q64Vec) {
+    let ((mass_a, inertia_a), (mass_b, inertia_b)) = (inverse[self.a], inverse[self.b]);
+    let a = &mut bodies[self.a];
+    a.velocity -= impulse * mass_a;
+    a.angular_velocity -= inertia_a * self.ra.cross(impulse);
+    let b = &mut bodies[self.b];
+    b.velocity += impulse * mass_b;
+    b.angular_velocity += inertia_b * self.rb.cross(impulse);
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct World {
+  pub gravity: Q64Vec,
+  /// The duration of a step, in seconds.
+  pub timestep: Q64,
+  /// The impulse solver's passes over the contacts per step. More make stacks stiffer.
+  pub iterations: u32,
+  bodies: Vec<Body>,
+  contacts: Vec<Contact>,
+}
+
+impl World {
+  /// An empty world without gravity, solving with eight iterations.
+  pub fn new(timestep: Q64) -> Self {
+    Self {
+      gravity: Q64Vec::ZERO,
+      timestep,
+      iterations: 8,
+      bodies: vec![],
+      contacts: vec![],
+    }
+  }
+
+  /// Panics if the body's mass or friction is negative; see `try_add_body`.
+  pub fn add_body(&mut self, body: Body) -> BodyId {
+    self.try_add_body(body).unwrap_or_else(|_| panic!("{}", INVALID_BODY))
+  }
+
+  /// Fails with `OutOfDomain` if the body's mass or friction is negative.
+  pub fn try_add_body(&mut self, body: Body) -> Result<BodyId, FixedError> {
+    body.validate()?;
+    self.bodies.push(body);
+    Ok(BodyId(self.bodies.len() - 1))
+  }
+
+  pub fn body(&self, id: BodyId) -> &Body {
+    &self.bodies[id.0]
+  }
+
+  /// `step` panics if this leaves the body with a negative mass or friction.
+  pub fn body_mut(&mut self, id: BodyId) -> &mut Body {
+    &mut self.bodies[id.0]
+  }
+
+  /// The bodies, indexed by `BodyId::index`.
+  pub fn bodies(&self) -> &[Body] {
+    &self.bodies
+  }
+
+  /// The contacts found by the last step, ordered by their pair of bodies.
+  pub fn contacts(&self) -> &[Contact] {
+    &self.contacts
+  }
+
+  /// Panics if a body has a negative mass or friction, which only `body_mut` can cause.
+  pub fn step(&mut self) {
+    if self.bodies.iter().any(|body| body.validate().is_err()) {
+      panic!("{}", INVALID_BODY);
+    }
+    let dt = self.timestep;
+    for body in self.bodies.iter_mut().filter(|body| !body.is_static()) {
+      body.velocity += self.gravity * dt;
+    }
+    self.contacts = self.find_contacts();
+    self.solve_velocities();
+    for body in &mut self.bodies {
+      body.position += body.velocity * dt;
+      body.angle = (body.angle + body.angular_velocity * dt).rem_euclid(Q64::PI_TIMES_2);
+    }
+    self.correct_positions();
+  }
+
+  fn find_contacts(&self) -> Vec<Contact> {
+    let shapes: Vec<Shape> = self.bodies.iter().map(Body::shape).collect();
+    let aabbs: Vec<Aabb> = shapes
+      .iter()
+      .map(|shape| match shape {
+        Shape::Circle(circle) => circle.aabb(),
+        Shape::Polygon(polygon) => polygon.aabb(),
+      })
+      .collect();
+    let mut contacts = vec![];
+    for (a, b) in overlapping_pairs(&aabbs) {
+      if self.bodies[a].is_static() && self.bodies[b].is_static() {
+        continue;
+      }
+      if let Some((normal, points)) = collide(&shapes[a], &shapes[b]) {
+        for (point, depth) in points {
+          contacts.push(Contact {
+            a: BodyId(a),
+            b: BodyId(b),
+            point,
+            normal,
+            depth,
+          });
+        }
+      }
+    }
+    contacts
+  }
+
+  fn solve_velocities(&mut self) {
+    let inverse: Vec<(Q64, Q64)> = self.bodies.iter().map(|body| (body.inverse_mass(), body.inverse_inertia())).collect();
+    let mut constraints: Vec<Constraint> = self
+      .contacts
+      .iter()
+      .map(|contact| {
+        let (a, b) = (contact.a.0, contact.b.0);
+        let (body_a, body_b) = (&self.bodies[a], &self.bodies[b]);
+        let ((mass_a, inertia_a), (mass_b, inertia_b)) = (inverse[a], inverse[b]);
+        let (ra, rb) = (contact.point - body_a.position, contact.point - body_b.position);
+        // The inverse of the effective mass along a direction.
+        let effective_mass = |direction: Q64Vec| {
+          let (ca, cb) = (ra.cross(direction), rb.cross(direction));
+          // Saturating, since the inverses of tiny bodies saturate.
+          let k = mass_a.saturating_add(mass_b);
+          let k = k.saturating_add(inertia_a.saturating_mul(ca).saturating_mul(ca));
+          match k.saturating_add(inertia_b.saturating_mul(cb).saturating_mul(cb)) {
+            Q64::ZERO => Q64::ZERO,
+            k => Q64::ONE / k,
+          }
+        };
+        let approach = (body_b.velocity_at(rb) - body_a.velocity_at(ra)).dot(contact.normal);
+        let restitution = body_a.restitution.max(body_b.restitution);
+        Constraint {
+          a,
+          b,
+          normal: contact.normal,
+          ra,
+          rb,
+          normal_mass: effective_mass(contact.normal),
+          tangent_mass: effective_mass(contact.normal.perp()),
+          target_speed: match approach < -RESTITUTION_THRESHOLD {
+            true => -restitution * approach,
+            false => Q64::ZERO,
+          },
+          friction: (body_a.friction * body_b.friction).sqrt(),
+          normal_impulse: Q64::ZERO,
+          tangent_impulse: Q64::ZERO,
+        }
+      })
+      .collect();
+
+    for _ in 0..self.iterations {
+      for c in &mut constraints {
+        // The total normal impulse may only push the bodies apart.
+        let delta = (c.target_speed - c.relative_velocity(&self.bodies).dot(c.normal)) * c.normal_mass;
+        let total = (c.normal_impulse + delta).max(Q64::ZERO);
+        c.apply(&mut self.bodies, &inverse, c.normal * (total - c.normal_impulse));
+        c.normal_impulse = total;
+
+        // The total friction impulse is bounded by the normal impulse.
+        let tangent = c.normal.perp();
+        let delta = -c.relative_velocity(&self.bodies).dot(tangent) * c.tangent_mass;
+        let limit = c.friction * c.normal_impulse;
+        let total = (c.tangent_impulse + delta).clamp(-limit, limit);
+        c.apply(&mut self.bodies, &inverse, tangent * (total - c.tangent_impulse));
+        c.tangent_impulse = total;
+      }
+    }
+  }
+
+  /// Moves overlapping bodies apart along the contact normals, in proportion to their inverse
+  /// masses, which stops resting bodies from sinking into each other. Each pair moves once, by its
+  /// deepest contact.
+  fn correct_positions(&mut self) {
+    for pair in self.contacts.chunk_by(|x, y| (x.a, x.b) == (y.a, y.b)) {
+      let deepest = pair.iter().fold(pair[0], |deepest, &contact| match contact.depth > deepest.depth {
+        true => contact,
+        false => deepest,
+      });
+      let (a, b) = (deepest.a.0, deepest.b.0);
+      let (mass_a, mass_b) = (self.bodies[a].inverse_mass(), self.bodies[b].inverse_mass());
+      let excess = deepest.depth - SLOP;
+      if excess <= Q64::ZERO {
+        continue;
+      }
+      let correction = deepest.normal * (excess * CORRECTION_FRACTION / mass_a.saturating_add(mass_b));
+      self.bodies[a].position -= correction * mass_a;
+      self.bodies[b].position += correction * mass_b;
+    }
+  }
+}
@@ -0,0 +1,172 @@
+// ==================== Backing integer types ====================
+
+mod sealed {
+  pub trait Sealed {}
+  impl Sealed for i32 {}
+  impl Sealed for i64 {}
+  impl Sealed for i128 {}
+}
+
+/// An integer type that can back a `Fixed`.
+///
+/// The methods here are the operations that need more bits than the backing type has, namely
+/// the multiply and divide that shift by the number of fractional bits. Each one returns the
+/// wrapped result along with whether it overflowed, in the style of `i64::overflowing_mul`.
+pub trait FixedRepr: Copy + sealed::Sealed {
+  /// Computes `(self * rhs) >> shift`, rounding toward negative infinity.
+  fn overflowing_mul_shr(self, rhs: Self, shift: u32) -> (Self, bool);
+
+  /// Computes `(self << shift) / rhs`, rounding toward zero. Panics if `rhs` is zero.
+  fn overflowing_shl_div(self, shift: u32, rhs: Self) -> (Self, bool);
+
+  /// Computes `(self << shift) / rhs`, rounding toward positive infinity. Panics if `rhs` is zero.
+  fn overflowing_shl_div_ceil(self, shift: u32, rhs: Self) -> (Self, bool);
+}
+
+macro_rules! impl_repr_with_wide {
+  ($repr:ty, $wide:ty) => {
+    impl FixedRepr for $repr {
+      fn overflowing_mul_shr(self, rhs: Self, shift: u32) -> (Self, bool) {
+        let wide = (self as $wide * rhs as $wide) >> shift;
+        (wide as $repr, wide != wide as $repr as $wide)
+      }
+
+      fn overflowing_shl_div(self, shift: u32, rhs: Self) -> (Self, bool) {
+        let wide = ((self as $wide) << shift) / rhs as $wide;
+        (wide as $repr, wide != wide as $repr as $wide)
+      }
+
+      fn overflowing_shl_div_ceil(self, shift: u32, rhs: Self) -> (Self, bool) {
+        let lhs = (self as $wide) << shift;
+        let rhs = rhs as $wide;
+        let mut wide = lhs / rhs;
+        if lhs % rhs != 0 && (lhs < 0) == (rhs < 0) {
+          wide += 1;
+        }
+        (wide as $repr, wide != wide as $repr as $wide)
+      }
+    }
+  };
+}
+
+impl_repr_with_wide!(i32, i64);
+impl_repr_with_wide!(i64, i128);
+
+// There is no native 256-bit integer, so for i128 we do the wide arithmetic on (hi, lo) pairs.
+// These are const, so that the `const_*` arithmetic of every layout can widen to i128 and use them.
+
+const LOW_64: u128 = u64::MAX as u128;
+
+pub(crate) const fn widening_mul_u128(a: u128, b: u128) -> (u128, u128) {
+  let (a1, a0) = (a >> 64, a & LOW_64);
+  let (b1, b0) = (b >> 64, b & LOW_64);
+  let p00 = a0 * b0;
+  let p01 = a0 * b1;
+  let p10 = a1 * b0;
+  let p11 = a1 * b1;
+  let mid = (p00 >> 64) + (p01 & LOW_64) + (p10 & LOW_64);
+  let lo = (p00 & LOW_64) | (mid << 64);
+  let hi = p11 + (p01 >> 64) + (p10 >> 64) + (mid >> 64);
+  (hi, lo)
+}
+
+const fn negate_u256((hi, lo): (u128, u128)) -> (u128, u128) {
+  let (lo, carry) = (!lo).overflowing_add(1);
+  ((!hi).wrapping_add(carry as u128), lo)
+}
+
+const fn shl_u256(x: u128, shift: u32) -> (u128, u128) {
+  match shift {
+    0 => (0, x),
+    _ => (x >> (128 - shift), x << shift),
+  }
+}
+
+/// Divides a 256-bit numerator by `d`, returning the low 128 bits of the quotient, whether the
+/// quotient needed more than 128 bits, and the remainder. Requires `0 < d <= 2^127`.
+pub(crate) const fn div_rem_u256((hi, lo): (u128, u128), d: u128) -> (u128, bool, u128) {
+  if hi == 0 {
+    return (lo / d, false, lo % d);
+  }
+  let mut q_hi = 0u128;
+  let mut q_lo = 0u128;
+  let mut r = 0u128;
+  let mut i = 256;
+  while i > 0 {
+    i -= 1;
+    let bit = match i >= 128 {
+      true => (hi >> (i - 128)) & 1,
+      false => (lo >> i) & 1,
+    };
+    // Because r < d <= 2^127 this shift can't overflow.
+    r = (r << 1) | bit;
+    if r >= d {
+      r -= d;
+      match i >= 128 {
+        true => q_hi |= 1 << (i - 128),
+        false => q_lo |= 1 << i,
+      }
+    }
+  }
+  (q_lo, q_hi != 0, r)
+}
+
+/// Applies the sign to a 128-bit magnitude, reporting whether the result fits in an i128.
+const fn signed_from_magnitude(magnitude: u128, too_big: bool, negative: bool) -> (i128, bool) {
+  match negative {
+    true => ((magnitude as i128).wrapping_neg(), too_big || magnitude > 1 << 127),
+    false => (magnitude as i128, too_big || magnitude > i128::MAX as u128),
+  }
+}
+
+/// `FixedRepr::overflowing_mul_shr` for i128.
+pub(crate) const fn overflowing_mul_shr_i128(lhs: i128, rhs: i128, shift: u32) -> (i128, bool) {
+  let mut product = widening_mul_u128(lhs.unsigned_abs(), rhs.unsigned_abs());
+  if (lhs < 0) != (rhs < 0) {
+    product = negate_u256(product);
+  }
+  // Arithmetic right shift of the two's complement 256-bit value, keeping the low 128 bits.
+  let (hi, lo) = product;
+  let result = match shift {
+    0 => lo,
+    _ => (lo >> shift) | (hi << (128 - shift)),
+  } as i128;
+  let remaining_hi = (hi as i128) >> shift;
+  (result, remaining_hi != result >> 127)
+}
+
+/// `FixedRepr::overflowing_shl_div` for i128.
+pub(crate) const fn overflowing_shl_div_i128(lhs: i128, shift: u32, rhs: i128) -> (i128, bool) {
+  if rhs == 0 {
+    panic!("attempt to divide by zero");
+  }
+  let numerator = shl_u256(lhs.unsigned_abs(), shift);
+  let (q, too_big, _) = div_rem_u256(numerator, rhs.unsigned_abs());
+  signed_from_magnitude(q, too_big, (lhs < 0) != (rhs < 0))
+}
+
+impl FixedRepr for i128 {
+  fn overflowing_mul_shr(self, rhs: Self, shift: u32) -> (Self, bool) {
+    overflowing_mul_shr_i128(self, rhs, shift)
+  }
+
+  fn overflowing_shl_div(self, shift: u32, rhs: Self) -> (Self, bool) {
+    overflowing_shl_div_i128(self, shift, rhs)
+  }
+
+  fn overflowing_shl_div_ceil(self, shift: u32, rhs: Self) -> (Self, bool) {
+    if rhs == 0 {
+      panic!("attempt to divide by zero");
+    }
+    let numerator = shl_u256(self.unsigned_abs(), shift);
+    let (mut q, mut too_big, r) = div_rem_u256(numerator, rhs.unsigned_abs());
+    let negative = (self < 0) != (rhs < 0);
+    // Truncation already rounds negative quotients up, so only positive ones need a bump.
+    if r != 0 && !negative {
+      let (bumped, carry) = q.overflowing_add(1);
+      q = bumped;
+      too_big |= carry;
+    }
+    signed_from_magnitude(q, too_big, negative)
+  }
+}
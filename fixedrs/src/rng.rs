@@ -0,0 +1,122 @@
+// ==================== Deterministic random numbers ====================
+
+use crate::{Q64Vec, Q64};
+
+/// A seedable pseudo-random number generator, xoshiro256** seeded through splitmix64, whose output
+/// depends only on the seed and the sequence of calls. Its whole state is the four words it
+/// serializes, so it can be snapshotted along with the rest of a simulation.
+///
+/// This is not cryptographically secure.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Q64Rng {
+  state: [u64; 4],
+}
+
+impl Q64Rng {
+  pub fn new(seed: u64) -> Self {
+    let mut x = seed;
+    let mut splitmix64 = || {
+      x = x.wrapping_add(0x9e3779b97f4a7c15);
+      let mut z = x;
+      z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+      z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+      z ^ (z >> 31)
+    };
+    // splitmix64 is a bijection of its counter, so at most one of these words is zero.
+    Self {
+      state: [splitmix64(), splitmix64(), splitmix64(), splitmix64()],
+    }
+  }
+
+  pub fn next_u64(&mut self) -> u64 {
+    let s = &mut self.state;
+    let result = s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+    let t = s[1] << 17;
+    s[2] ^= s[0];
+    s[3] ^= s[1];
+    s[1] ^= s[2];
+    s[0] ^= s[3];
+    s[2] ^= t;
+    s[3] = s[3].rotate_left(45);
+    result
+  }
+
+  /// The high bits of `next_u64`, which are the best ones.
+  pub fn next_u32(&mut self) -> u32 {
+    (self.next_u64() >> 32) as u32
+  }
+
+  /// Uniform in [0, 1), with every multiple of `Q64::EPSILON` equally likely.
+  pub fn next_q64_unit(&mut self) -> Q64 {
+    Q64::from_bits(self.next_u32() as i64)
+  }
+
+  /// Uniform in [0, n), without bias, by Lemire's multiply-and-reject method. `n` must be nonzero.
+  fn below(&mut self, n: u64) -> u64 {
+    let threshold = n.wrapping_neg() % n;
+    loop {
+      let product = self.next_u64() as u128 * n as u128;
+      if product as u64 >= threshold {
+        return (product >> 64) as u64;
+      }
+    }
+  }
+
+  /// Uniform in [lo, hi), with every representable value equally likely. Panics unless `lo < hi`.
+  pub fn range(&mut self, lo: Q64, hi: Q64) -> Q64 {
+    assert!(lo < hi, "empty range");
+    let span = hi.0.wrapping_sub(lo.0) as u64;
+    Q64::from_bits(lo.0.wrapping_add(self.below(span) as i64))
+  }
+
+  /// A uniformly random direction, as a vector of norm one up to rounding.
+  pub fn unit_vector(&mut self) -> Q64Vec {
+    self.range(Q64::ZERO, Q64::PI_TIMES_2).angle_to_vec()
+  }
+
+  /// A sample from the normal distribution with mean zero and standard deviation one, by the
+  /// Box-Muller transform. The magnitude never exceeds about 6.7, since the uniform input is
+  /// discretized.
+  pub fn standard_normal(&mut self) -> Q64 {
+    // In (0, 1], so that the logarithm is finite.
+    let u = Q64::ONE - self.next_q64_unit();
+    let radius = (-Q64::from_i32(2) * u.ln().unwrap()).sqrt();
+    radius * self.range(Q64::ZERO, Q64::PI_TIMES_2).cos()
+  }
+
+  pub fn gaussian(&mut self, mean: Q64, std_dev: Q64) -> Q64 {
+    mean + std_dev * self.standard_normal()
+  }
+}
+
+/// Feeds the four state words, so that generators checksum equal exactly when they will produce
+/// the same numbers.
+impl crate::checksum::StableHash for Q64Rng {
+  fn stable_hash(&self, hasher: &mut crate::checksum::StableHasher) {
+    self.state.stable_hash(hasher);
+  }
+}
+
+// The state words are serialized as a string, like the other types here, because JSON can't hold
+// 64-bit integers exactly.
+
+impl ::serde::Serialize for Q64Rng {
+  fn serialize<S: ::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    let [a, b, c, d] = self.state;
+    serializer.serialize_str(&format!("{},{},{},{}", a, b, c, d))
+  }
+}
+
+impl<'de> ::serde::Deserialize<'de> for Q64Rng {
+  fn deserialize<D: ::serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    use ::serde::de::Error;
+    let s = String::deserialize(deserializer)?;
+    let words = s.split(',').map(|word| word.parse::<u64>().map_err(D::Error::custom)).collect::<Result<Vec<_>, _>>()?;
+    let state: [u64; 4] = words.try_into().map_err(|_| D::Error::custom("expected four state words"))?;
+    // The all-zero state is a fixed point, which no seed produces.
+    if state == [0; 4] {
+      return Err(D::Error::custom("all-zero state"));
+    }
+    Ok(Self { state })
+  }
+}
@@ -0,0 +1,231 @@
+// ==================== Serde representations ====================
+
+//! Alternative serde representations, for use with `#[serde(with = "...")]`.
+//!
+//! By default fixed-point values serialize as a string of their raw bits, and `Q64Vec` as a string
+//! `"x,y"` of raw bits, which is exact but unreadable. The modules here offer other choices:
+//!
+//! - `decimal`: the exact decimal value as a string, such as `"-12.375"`.
+//! - `raw_int`: the raw bits as an integer, for binary formats.
+//! - `float_lossy`: the value as an f64, rounded to nearest when deserializing.
+//! - `struct_form`: for vectors, the struct `{x, y}` with coordinates in the default representation.
+//!
+//! Every module works for any `Fixed` layout and for `Q64Vec`, which always uses the struct form
+//! `{x, y}` with its coordinates in the chosen representation.
+//!
+//! ```
+//! #[derive(serde::Serialize, serde::Deserialize)]
+//! struct Spawn {
+//!   #[serde(with = "fixedrs::serde::decimal")]
+//!   position: fixedrs::Q64Vec,
+//!   #[serde(with = "fixedrs::serde::decimal")]
+//!   heading: fixedrs::Q64,
+//! }
+//! ```
+
+use crate::{Fixed, FixedError, ParseFixedError, Q64Vec, Q64};
+use ::serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::marker::PhantomData;
+
+mod imp {
+  use super::*;
+
+  /// A scalar type with every representation available.
+  pub trait Scalar: Copy + std::fmt::Display + std::str::FromStr<Err = ParseFixedError> + Serialize {
+    fn deserialize_default<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error>;
+    fn serialize_raw_int<S: Serializer>(self, serializer: S) -> Result<S::Ok, S::Error>;
+    fn deserialize_raw_int<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error>;
+    fn to_f64(self) -> f64;
+    fn try_from_f64(x: f64) -> Result<Self, FixedError>;
+  }
+
+  macro_rules! impl_scalar {
+    ($repr:ty) => {
+      impl<const FRAC: u32> Scalar for Fixed<$repr, FRAC> {
+        fn deserialize_default<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+          Self::deserialize(deserializer)
+        }
+
+        fn serialize_raw_int<S: Serializer>(self, serializer: S) -> Result<S::Ok, S::Error> {
+          self.0.serialize(serializer)
+        }
+
+        fn deserialize_raw_int<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+          <$repr>::deserialize(deserializer).map(Self)
+        }
+
+        fn to_f64(self) -> f64 {
+          Self::to_f64(self)
+        }
+
+        fn try_from_f64(x: f64) -> Result<Self, FixedError> {
+          Self::try_from_f64(x)
+        }
+      }
+    };
+  }
+
+  impl_scalar!(i32);
+  impl_scalar!(i64);
+  impl_scalar!(i128);
+
+  /// One of the representations, as a marker type.
+  pub trait Representation {
+    fn serialize<T: Scalar, S: Serializer>(x: T, serializer: S) -> Result<S::Ok, S::Error>;
+    fn deserialize<'de, T: Scalar, D: Deserializer<'de>>(deserializer: D) -> Result<T, D::Error>;
+  }
+
+  pub struct Default;
+  pub struct Decimal;
+  pub struct RawInt;
+  pub struct FloatLossy;
+
+  impl Representation for Default {
+    fn serialize<T: Scalar, S: Serializer>(x: T, serializer: S) -> Result<S::Ok, S::Error> {
+      x.serialize(serializer)
+    }
+
+    fn deserialize<'de, T: Scalar, D: Deserializer<'de>>(deserializer: D) -> Result<T, D::Error> {
+      T::deserialize_default(deserializer)
+    }
+  }
+
+  impl Representation for Decimal {
+    fn serialize<T: Scalar, S: Serializer>(x: T, serializer: S) -> Result<S::Ok, S::Error> {
+      serializer.collect_str(&x)
+    }
+
+    fn deserialize<'de, T: Scalar, D: Deserializer<'de>>(deserializer: D) -> Result<T, D::Error> {
+      let s = String::deserialize(deserializer)?;
+      s.parse().map_err(::serde::de::Error::custom)
+    }
+  }
+
+  impl Representation for RawInt {
+    fn serialize<T: Scalar, S: Serializer>(x: T, serializer: S) -> Result<S::Ok, S::Error> {
+      x.serialize_raw_int(serializer)
+    }
+
+    fn deserialize<'de, T: Scalar, D: Deserializer<'de>>(deserializer: D) -> Result<T, D::Error> {
+      T::deserialize_raw_int(deserializer)
+    }
+  }
+
+  impl Representation for FloatLossy {
+    fn serialize<T: Scalar, S: Serializer>(x: T, serializer: S) -> Result<S::Ok, S::Error> {
+      serializer.serialize_f64(x.to_f64())
+    }
+
+    fn deserialize<'de, T: Scalar, D: Deserializer<'de>>(deserializer: D) -> Result<T, D::Error> {
+      T::try_from_f64(f64::deserialize(deserializer)?).map_err(::serde::de::Error::custom)
+    }
+  }
+
+  /// A scalar that (de)serializes in the representation `R`.
+  pub struct As<R, T>(pub T, PhantomData<R>);
+
+  impl<R, T> As<R, T> {
+    pub fn new(x: T) -> Self {
+      Self(x, PhantomData)
+    }
+  }
+
+  impl<R: Representation, T: Scalar> Serialize for As<R, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+      R::serialize(self.0, serializer)
+    }
+  }
+
+  impl<'de, R: Representation, T: Scalar> Deserialize<'de> for As<R, T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+      R::deserialize(deserializer).map(Self::new)
+    }
+  }
+
+  #[derive(Serialize, Deserialize)]
+  #[serde(rename = "Q64Vec")]
+  pub struct VecForm<X> {
+    pub x: X,
+    pub y: X,
+  }
+}
+
+use imp::{As, Representation, Scalar, VecForm};
+
+/// A type that the modules in `fixedrs::serde` can (de)serialize. This is sealed: it is
+/// implemented for every `Fixed` layout and for `Q64Vec`.
+pub trait Representable: Sized {
+  #[doc(hidden)]
+  fn serialize_as<R: Representation, S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>;
+  #[doc(hidden)]
+  fn deserialize_as<'de, R: Representation, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error>;
+}
+
+impl<T: Scalar> Representable for T {
+  fn serialize_as<R: Representation, S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    R::serialize(*self, serializer)
+  }
+
+  fn deserialize_as<'de, R: Representation, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    R::deserialize(deserializer)
+  }
+}
+
+impl Representable for Q64Vec {
+  fn serialize_as<R: Representation, S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    VecForm {
+      x: As::<R, Q64>::new(self.x),
+      y: As::<R, Q64>::new(self.y),
+    }
+    .serialize(serializer)
+  }
+
+  fn deserialize_as<'de, R: Representation, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    let form = VecForm::<As<R, Q64>>::deserialize(deserializer)?;
+    Ok(Q64Vec::new(form.x.0, form.y.0))
+  }
+}
+
+macro_rules! representation_module {
+  ($(#[$doc:meta])* $name:ident, $representation:ty) => {
+    $(#[$doc])*
+    pub mod $name {
+      use super::*;
+
+      pub fn serialize<T: Representable, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+        value.serialize_as::<$representation, S>(serializer)
+      }
+
+      pub fn deserialize<'de, T: Representable, D: Deserializer<'de>>(deserializer: D) -> Result<T, D::Error> {
+        T::deserialize_as::<$representation, D>(deserializer)
+      }
+    }
+  };
+}
+
+representation_module!(
+  /// The exact decimal value as a string, such as `"-12.375"`. Deserializing accepts anything that
+  /// `FromStr` does, rounding to the nearest representable value.
+  decimal,
+  imp::Decimal
+);
+
+representation_module!(
+  /// The raw bits as an integer of the backing type.
+  raw_int,
+  imp::RawInt
+);
+
+representation_module!(
+  /// The value as an f64, which is lossy for values that need more than 53 significant bits.
+  /// Deserializing rounds to nearest and rejects NaN, infinities and out-of-range values.
+  float_lossy,
+  imp::FloatLossy
+);
+
+representation_module!(
+  /// For vectors, the struct `{x, y}` with coordinates in the default raw-string representation.
+  /// Scalars are unaffected.
+  struct_form,
+  imp::Default
+);
@@ -0,0 +1,277 @@
+// ==================== Spatial indexes ====================
+
+//! Spatial indexes over points keyed by `Q64Vec`, for proximity queries.
+//!
+//! `SpatialGrid` buckets points into square cells and suits points that move every tick, since
+//! inserting is cheap. `KdTree` is built once from a fixed set of points and answers queries faster
+//! when the points are clustered.
+//!
+//! Both answer the same queries with the same results in the same order, which depends only on the
+//! points and the order they were added, never on hashing or memory layout:
+//!
+//! - `within_radius` and `nearest` return points by increasing distance, with ties going to the
+//!   point added first.
+//! - `within_aabb` returns points in the order they were added.
+//!
+//! Distances are those of `Q64Vec::distance`, but computed in 128 bits like the circle tests in
+//! `geometry`, so that points any distance apart compare correctly. A point exactly `radius` away
+//! is within it.
+
+use crate::geometry::{distance_bits, Aabb};
+use crate::{Q64Vec, Q64};
+use std::collections::BTreeMap;
+
+/// The radius in raw units, or None if it is negative, when nothing is within it.
+fn radius_bits(radius: Q64) -> Option<u128> {
+  u128::try_from(radius.0).ok()
+}
+
+/// Orders candidates by increasing distance, ties going to the earlier index.
+fn sort_by_distance<T>(center: Q64Vec, found: &mut [(usize, Q64Vec, &T)]) {
+  found.sort_unstable_by_key(|&(index, position, _)| (distance_bits(position, center), index));
+}
+
+fn strip_index<T>(found: Vec<(usize, Q64Vec, &T)>) -> Vec<(Q64Vec, &T)> {
+  found.into_iter().map(|(_, position, value)| (position, value)).collect()
+}
+
+// ==================== SpatialGrid ====================
+
+/// A uniform grid hash: points are bucketed by the square cell of side `cell_size` that contains
+/// them. Queries visit only the occupied cells that overlap the query region, so a cell size near
+/// the typical query radius works best.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SpatialGrid<T> {
+  cell_size: Q64,
+  entries: Vec<(Q64Vec, T)>,
+  /// The indices into `entries` of the points in each occupied cell, keyed by cell coordinates.
+  cells: BTreeMap<(i64, i64), Vec<usize>>,
+}
+
+impl<T> SpatialGrid<T> {
+  /// Panics unless `cell_size` is positive.
+  pub fn new(cell_size: Q64) -> Self {
+    assert!(cell_size > Q64::ZERO, "cell size must be positive");
+    Self {
+      cell_size,
+      entries: vec![],
+      cells: BTreeMap::new(),
+    }
+  }
+
+  pub fn cell_size(&self) -> Q64 {
+    self.cell_size
+  }
+
+  pub fn len(&self) -> usize {
+    self.entries.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.entries.is_empty()
+  }
+
+  /// Removes every point, keeping the cell size.
+  pub fn clear(&mut self) {
+    self.entries.clear();
+    self.cells.clear();
+  }
+
+  pub fn insert(&mut self, position: Q64Vec, value: T) {
+    self.cells.entry(self.cell_of(position)).or_default().push(self.entries.len());
+    self.entries.push((position, value));
+  }
+
+  /// The points in the order they were added.
+  pub fn iter(&self) -> impl Iterator<Item = (Q64Vec, &T)> + '_ {
+    self.entries.iter().map(|(position, value)| (*position, value))
+  }
+
+  fn cell_of(&self, p: Q64Vec) -> (i64, i64) {
+    (p.x.0.div_euclid(self.cell_size.0), p.y.0.div_euclid(self.cell_size.0))
+  }
+
+  /// The points in the cells overlapping the box, with their indices.
+  fn candidates(&self, min: Q64Vec, max: Q64Vec) -> Vec<(usize, Q64Vec, &T)> {
+    let ((x0, y0), (x1, y1)) = (self.cell_of(min), self.cell_of(max));
+    // Cells are ordered by column, so this range holds exactly the columns x0..=x1, but all of
+    // their rows.
+    let cells = self.cells.range((x0, y0)..=(x1, y1)).filter(|((_, y), _)| (y0..=y1).contains(y));
+    let indices = cells.flat_map(|(_, indices)| indices.iter().copied());
+    indices.map(|i| (i, self.entries[i].0, &self.entries[i].1)).collect()
+  }
+
+  /// The points within `radius` of `center`, nearest first.
+  pub fn within_radius(&self, center: Q64Vec, radius: Q64) -> Vec<(Q64Vec, &T)> {
+    let Some(limit) = radius_bits(radius) else { return vec![] };
+    let extent = Q64Vec::new(radius, radius);
+    let mut found = self.candidates(center.saturating_sub(extent), center.saturating_add(extent));
+    found.retain(|&(_, position, _)| distance_bits(position, center) <= limit);
+    sort_by_distance(center, &mut found);
+    strip_index(found)
+  }
+
+  /// The `k` points nearest to `center`, nearest first, or all of them if there are fewer.
+  pub fn nearest(&self, center: Q64Vec, k: usize) -> Vec<(Q64Vec, &T)> {
+    // Once some radius holds at least k points, it holds the k nearest.
+    let mut radius = self.cell_size;
+    while radius < Q64::MAX {
+      let mut found = self.within_radius(center, radius);
+      if found.len() >= k.min(self.len()) {
+        found.truncate(k);
+        return found;
+      }
+      radius = radius.saturating_mul(Q64::from_i32(2));
+    }
+    // Points can be farther apart than any Q64, so past that look at all of them.
+    let mut found: Vec<_> =
+      self.entries.iter().enumerate().map(|(i, (position, value))| (i, *position, value)).collect();
+    sort_by_distance(center, &mut found);
+    found.truncate(k);
+    strip_index(found)
+  }
+
+  /// The points inside the box, in the order they were added.
+  pub fn within_aabb(&self, aabb: &Aabb) -> Vec<(Q64Vec, &T)> {
+    let mut found = self.candidates(aabb.min, aabb.max);
+    found.retain(|&(_, position, _)| aabb.contains_point(position));
+    found.sort_unstable_by_key(|&(index, _, _)| index);
+    strip_index(found)
+  }
+}
+
+// ==================== KdTree ====================
+
+/// A 2-d tree over a fixed set of points, stored as a sorted array: the median of each range along
+/// the splitting axis sits in its middle, with the lesser points before it and the greater after.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct KdTree<T> {
+  /// The points with the order they were added in.
+  entries: Vec<(usize, Q64Vec, T)>,
+}
+
+/// The coordinate that splits the tree at `depth`: x at even depths, y at odd ones.
+fn axis(p: Q64Vec, depth: usize) -> Q64 {
+  match depth % 2 {
+    0 => p.x,
+    _ => p.y,
+  }
+}
+
+fn build<T>(entries: &mut [(usize, Q64Vec, T)], depth: usize) {
+  if entries.len() <= 1 {
+    return;
+  }
+  let mid = entries.len() / 2;
+  // The index breaks ties, so the split is the same however the selection works.
+  entries.select_nth_unstable_by_key(mid, |&(index, position, _)| (axis(position, depth), index));
+  let (left, right) = entries.split_at_mut(mid);
+  build(left, depth + 1);
+  build(&mut right[1..], depth + 1);
+}
+
+impl<T> KdTree<T> {
+  pub fn new(points: Vec<(Q64Vec, T)>) -> Self {
+    let mut entries: Vec<_> = points.into_iter().enumerate().map(|(i, (position, value))| (i, position, value)).collect();
+    build(&mut entries, 0);
+    Self { entries }
+  }
+
+  pub fn len(&self) -> usize {
+    self.entries.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.entries.is_empty()
+  }
+
+  /// The points within `radius` of `center`, nearest first.
+  pub fn within_radius(&self, center: Q64Vec, radius: Q64) -> Vec<(Q64Vec, &T)> {
+    fn visit<'a, T>(
+      entries: &'a [(usize, Q64Vec, T)],
+      depth: usize,
+      center: Q64Vec,
+      radius: u128,
+      found: &mut Vec<(usize, Q64Vec, &'a T)>,
+    ) {
+      let Some((index, position, value)) = entries.get(entries.len() / 2) else { return };
+      if distance_bits(*position, center) <= radius {
+        found.push((*index, *position, value));
+      }
+      // In i128, since the offset can overflow.
+      let offset = axis(center, depth).0 as i128 - axis(*position, depth).0 as i128;
+      let (left, right) = entries.split_at(entries.len() / 2);
+      if offset <= radius as i128 {
+        visit(left, depth + 1, center, radius, found);
+      }
+      if offset >= -(radius as i128) {
+        visit(&right[1..], depth + 1, center, radius, found);
+      }
+    }
+    let Some(radius) = radius_bits(radius) else { return vec![] };
+    let mut found = vec![];
+    visit(&self.entries, 0, center, radius, &mut found);
+    sort_by_distance(center, &mut found);
+    strip_index(found)
+  }
+
+  /// The `k` points nearest to `center`, nearest first, or all of them if there are fewer.
+  pub fn nearest(&self, center: Q64Vec, k: usize) -> Vec<(Q64Vec, &T)> {
+    /// Keeps `best` as the k nearest points seen so far, sorted by distance and then index.
+    fn visit<'a, T>(
+      entries: &'a [(usize, Q64Vec, T)],
+      depth: usize,
+      center: Q64Vec,
+      k: usize,
+      best: &mut Vec<(u128, usize, Q64Vec, &'a T)>,
+    ) {
+      let Some((index, position, value)) = entries.get(entries.len() / 2) else { return };
+      let key = (distance_bits(*position, center), *index);
+      let at = best.partition_point(|&(distance, index, _, _)| (distance, index) < key);
+      if at < k {
+        best.insert(at, (key.0, key.1, *position, value));
+        best.truncate(k);
+      }
+      let offset = axis(center, depth).0 as i128 - axis(*position, depth).0 as i128;
+      let (left, right) = entries.split_at(entries.len() / 2);
+      let (near, far) = match offset <= 0 {
+        true => (left, &right[1..]),
+        false => (&right[1..], left),
+      };
+      visit(near, depth + 1, center, k, best);
+      // The far side is at least |offset| away, and a point exactly as far as the worst one kept
+      // can still win on index.
+      if best.len() < k || offset.unsigned_abs() <= best[k - 1].0 {
+        visit(far, depth + 1, center, k, best);
+      }
+    }
+    if k == 0 {
+      return vec![];
+    }
+    let mut best = Vec::with_capacity(k + 1);
+    visit(&self.entries, 0, center, k, &mut best);
+    best.into_iter().map(|(_, _, position, value)| (position, value)).collect()
+  }
+
+  /// The points inside the box, in the order they were added.
+  pub fn within_aabb(&self, aabb: &Aabb) -> Vec<(Q64Vec, &T)> {
+    fn visit<'a, T>(entries: &'a [(usize, Q64Vec, T)], depth: usize, aabb: &Aabb, found: &mut Vec<(usize, Q64Vec, &'a T)>) {
+      let Some((index, position, value)) = entries.get(entries.len() / 2) else { return };
+      if aabb.contains_point(*position) {
+        found.push((*index, *position, value));
+      }
+      let split = axis(*position, depth);
+      let (left, right) = entries.split_at(entries.len() / 2);
+      if axis(aabb.min, depth) <= split {
+        visit(left, depth + 1, aabb, found);
+      }
+      if axis(aabb.max, depth) >= split {
+        visit(&right[1..], depth + 1, aabb, found);
+      }
+    }
+    let mut found = vec![];
+    visit(&self.entries, 0, aabb, &mut found);
+    found.sort_unstable_by_key(|&(index, _, _)| index);
+    strip_index(found)
+  }
+}
@@ -0,0 +1,143 @@
+// ==================== Accurate trig kernels ====================
+
+// The accurate trig functions do their work in Q2.62, independent of the layout of the caller, so
+// that every layout with at most 62 fractional bits gets a result within about half an ulp.
+
+pub(crate) const Q62_SHIFT: u32 = 62;
+
+// PI / 2 split into two parts so that multiples of it can be subtracted with 124 bits of precision.
+const PI_OVER_TWO_Q62_HI: i128 = 7244019458077122842;
+const PI_OVER_TWO_Q62_LO: i128 = 1772391103515558810;
+
+// Taylor coefficients 1/n! with alternating signs, in Q2.62. Past the last term the series is below
+// 2^-62 on [-PI/4, PI/4].
+const SIN_COEFFICIENTS: [i64; 10] = [
+  4611686018427387904, -768614336404564651, 38430716820228233, -915017067148291, 12708570377060,
+  -115532457973, 740592679, -3526632, 12966, -38,
+];
+const COS_COEFFICIENTS: [i64; 11] = [
+  4611686018427387904, -2305843009213693952, 192153584101141163, -6405119470038039, 114377133393536,
+  -1270857037706, 9627704831, -52899477, 220414, -720, 2,
+];
+
+pub(crate) fn mul_q62(a: i64, b: i64) -> i64 {
+  ((a as i128 * b as i128) >> Q62_SHIFT) as i64
+}
+
+/// Evaluates the even polynomial with the given coefficients at `r`, via Horner's rule in r^2.
+pub(crate) fn even_poly_q62(coefficients: &[i64], r2: i64) -> i64 {
+  coefficients.iter().rev().fold(0, |acc, &c| c + mul_q62(r2, acc))
+}
+
+/// Splits an angle in Q.62 (which must be less than 2^64 in magnitude) into a quadrant in 0..4 and
+/// a remainder in [-PI/4, PI/4] in Q2.62, such that `x = quadrant * PI/2 + remainder (mod 2 PI)`.
+pub(crate) fn reduce_q62(x: i128) -> (u32, i64) {
+  let q = (x + (PI_OVER_TWO_Q62_HI >> 1)).div_euclid(PI_OVER_TWO_Q62_HI);
+  let r = x - q * PI_OVER_TWO_Q62_HI - ((q * PI_OVER_TWO_Q62_LO) >> Q62_SHIFT);
+  (q.rem_euclid(4) as u32, r as i64)
+}
+
+/// sin(r) for r in [-PI/4, PI/4], in Q2.62.
+pub(crate) fn sin_q62(r: i64) -> i64 {
+  mul_q62(r, even_poly_q62(&SIN_COEFFICIENTS, mul_q62(r, r)))
+}
+
+/// cos(r) for r in [-PI/4, PI/4], in Q2.62.
+pub(crate) fn cos_q62(r: i64) -> i64 {
+  even_poly_q62(&COS_COEFFICIENTS, mul_q62(r, r))
+}
+
+/// sin and cos of the angle `quadrant * PI/2 + r`, in Q2.62, sharing the work of squaring `r`.
+pub(crate) fn sin_cos_quadrant_q62(quadrant: u32, r: i64) -> (i64, i64) {
+  let r2 = mul_q62(r, r);
+  let sin = mul_q62(r, even_poly_q62(&SIN_COEFFICIENTS, r2));
+  let cos = even_poly_q62(&COS_COEFFICIENTS, r2);
+  match quadrant {
+    0 => (sin, cos),
+    1 => (cos, -sin),
+    2 => (-sin, -cos),
+    _ => (-cos, sin),
+  }
+}
+
+/// sin of the angle `quadrant * PI/2 + r`, in Q2.62.
+pub(crate) fn sin_quadrant_q62(quadrant: u32, r: i64) -> i64 {
+  match quadrant {
+    0 => sin_q62(r),
+    1 => cos_q62(r),
+    2 => -sin_q62(r),
+    _ => -cos_q62(r),
+  }
+}
+
+// atan(k / 16) for k in 0..=16, in Q2.62.
+const ATAN_TABLE: [i64; 17] = [
+  0, 287855953345232185, 573486189672913778, 854766549539324179, 1129764675555192497,
+  1396809912678075341, 1654537683908620430, 1901907467368135863, 2138197195906305897,
+  2362979310104584075, 2576084652473018343, 2777560133246350984, 2967625066264511049,
+  3146629716257419296, 3315018258881210269, 3473297232174029200, 3622009729038561421,
+];
+
+// Taylor coefficients (-1)^n / (2n + 1) of atan, in Q2.62. After reduction |u| <= 1/32, so the
+// series is below 2^-62 past the last term.
+const ATAN_COEFFICIENTS: [i64; 7] = [
+  4611686018427387904, -1537228672809129301, 922337203685477581, -658812288346769701,
+  512409557603043100, -419244183493398900, 354745078340568300,
+];
+
+/// atan(t) for t in [0, 1], in Q2.62, using atan(t) = atan(c) + atan((t - c) / (1 + t c)) with c
+/// the nearest multiple of 1/16.
+fn atan_unit_q62(t: i64) -> i64 {
+  let k = ((t + (1 << 57)) >> 58) as usize;
+  let c = (k as i64) << 58;
+  let numerator = ((t - c) as i128) << Q62_SHIFT;
+  let denominator = (1i128 << Q62_SHIFT) + ((t as i128 * c as i128) >> Q62_SHIFT);
+  let u = (numerator / denominator) as i64;
+  ATAN_TABLE[k] + mul_q62(u, even_poly_q62(&ATAN_COEFFICIENTS, mul_q62(u, u)))
+}
+
+/// `num / den` in Q2.62, for `num <= den` and `den > 0`.
+fn unit_ratio_q62(mut num: u128, mut den: u128) -> i64 {
+  // Drop low bits that would overflow the shift; the ratio keeps at least 66 significant bits.
+  let excess_bits = (128 - den.leading_zeros()).saturating_sub(66);
+  num >>= excess_bits;
+  den >>= excess_bits;
+  ((num << Q62_SHIFT) / den) as i64
+}
+
+/// atan2(y, x) in (-PI, PI], in Q.62. The inputs may have any common scale.
+pub(crate) fn atan2_q62(y: i128, x: i128) -> i128 {
+  let (y_abs, x_abs) = (y.unsigned_abs(), x.unsigned_abs());
+  if y_abs == 0 && x_abs == 0 {
+    return 0;
+  }
+  // The low part of PI / 2 is below half an ulp of Q.62, so the high part alone is correctly rounded.
+  let mut angle = match y_abs > x_abs {
+    true => PI_OVER_TWO_Q62_HI - atan_unit_q62(unit_ratio_q62(x_abs, y_abs)) as i128,
+    false => atan_unit_q62(unit_ratio_q62(y_abs, x_abs)) as i128,
+  };
+  if x < 0 {
+    angle = 2 * PI_OVER_TWO_Q62_HI - angle;
+  }
+  if y < 0 {
+    angle = -angle;
+  }
+  angle
+}
+
+/// sqrt(1 - x^2) in Q2.62, for x in [-1, 1] in Q2.62.
+pub(crate) fn sqrt_one_minus_square_q62(x: i64) -> i64 {
+  use num_integer::Roots;
+  let one = 1i128 << Q62_SHIFT;
+  // (1 - x)(1 + x) is exact in Q.124, and its square root is then directly in Q.62.
+  let product = ((one - x as i128) * (one + x as i128)) as u128;
+  product.sqrt() as i64
+}
+
+/// Rescales a Q.62 value to `frac` fractional bits, rounding to nearest.
+pub(crate) fn from_q62(y: i128, frac: u32) -> i128 {
+  match frac >= Q62_SHIFT {
+    true => y << (frac - Q62_SHIFT),
+    false => (y + (1 << (Q62_SHIFT - frac - 1))) >> (Q62_SHIFT - frac),
+  }
+}
@@ -0,0 +1,481 @@
+// ==================== 3D vectors and quaternions ====================
+
+use crate::{norm_bits, normalized_bits, parse_raw_parts, FixedError, Q64};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Q64Vec3 {
+  pub x: Q64,
+  pub y: Q64,
+  pub z: Q64,
+}
+
+impl Q64Vec3 {
+  pub const ZERO: Self = Self {
+    x: Q64::ZERO,
+    y: Q64::ZERO,
+    z: Q64::ZERO,
+  };
+
+  pub const fn new(x: Q64, y: Q64, z: Q64) -> Self {
+    Self { x, y, z }
+  }
+
+  pub const fn from_i32(x: i32, y: i32, z: i32) -> Self {
+    Self {
+      x: Q64::from_i32(x),
+      y: Q64::from_i32(y),
+      z: Q64::from_i32(z),
+    }
+  }
+
+  pub const fn from_f64(x: f64, y: f64, z: f64) -> Self {
+    Self {
+      x: Q64::from_f64(x),
+      y: Q64::from_f64(y),
+      z: Q64::from_f64(z),
+    }
+  }
+
+  pub fn from_f64_round(x: f64, y: f64, z: f64) -> Self {
+    Self {
+      x: Q64::from_f64_round(x),
+      y: Q64::from_f64_round(y),
+      z: Q64::from_f64_round(z),
+    }
+  }
+
+  pub fn try_from_f64(x: f64, y: f64, z: f64) -> Result<Self, FixedError> {
+    Ok(Self {
+      x: Q64::try_from_f64(x)?,
+      y: Q64::try_from_f64(y)?,
+      z: Q64::try_from_f64(z)?,
+    })
+  }
+
+  pub fn to_f64(self) -> (f64, f64, f64) {
+    (self.x.to_f64(), self.y.to_f64(), self.z.to_f64())
+  }
+
+  fn to_bits(self) -> [i64; 3] {
+    [self.x.to_bits(), self.y.to_bits(), self.z.to_bits()]
+  }
+
+  fn from_bits([x, y, z]: [i64; 3]) -> Self {
+    Self::new(Q64::from_bits(x), Q64::from_bits(y), Q64::from_bits(z))
+  }
+
+  pub fn dot(self, rhs: Self) -> Q64 {
+    self.x * rhs.x + self.y * rhs.y + self.z * rhs.z
+  }
+
+  pub fn cross(self, rhs: Self) -> Self {
+    Self {
+      x: self.y * rhs.z - self.z * rhs.y,
+      y: self.z * rhs.x - self.x * rhs.z,
+      z: self.x * rhs.y - self.y * rhs.x,
+    }
+  }
+
+  pub fn norm_squared(self) -> Q64 {
+    self.dot(self)
+  }
+
+  /// Rounds toward zero like `sqrt`, computing the squares exactly so that nothing overflows unless
+  /// the norm itself is out of range, in which case it saturates.
+  pub fn norm(self) -> Q64 {
+    Q64::from_bits(norm_bits(&self.to_bits()).min(i64::MAX as u128) as i64)
+  }
+
+  /// Each coordinate is rounded toward zero, for any input. The zero vector normalizes to zero.
+  pub fn normalized(self) -> Self {
+    Self::from_bits(normalized_bits(self.to_bits()))
+  }
+
+  pub fn overflowing_add(self, rhs: Self) -> (Self, bool) {
+    let (x, x_overflow) = self.x.overflowing_add(rhs.x);
+    let (y, y_overflow) = self.y.overflowing_add(rhs.y);
+    let (z, z_overflow) = self.z.overflowing_add(rhs.z);
+    (Self { x, y, z }, x_overflow || y_overflow || z_overflow)
+  }
+
+  pub fn overflowing_sub(self, rhs: Self) -> (Self, bool) {
+    let (x, x_overflow) = self.x.overflowing_sub(rhs.x);
+    let (y, y_overflow) = self.y.overflowing_sub(rhs.y);
+    let (z, z_overflow) = self.z.overflowing_sub(rhs.z);
+    (Self { x, y, z }, x_overflow || y_overflow || z_overflow)
+  }
+
+  pub fn overflowing_mul(self, rhs: Q64) -> (Self, bool) {
+    let (x, x_overflow) = self.x.overflowing_mul(rhs);
+    let (y, y_overflow) = self.y.overflowing_mul(rhs);
+    let (z, z_overflow) = self.z.overflowing_mul(rhs);
+    (Self { x, y, z }, x_overflow || y_overflow || z_overflow)
+  }
+
+  pub fn overflowing_div(self, rhs: Q64) -> (Self, bool) {
+    let (x, x_overflow) = self.x.overflowing_div(rhs);
+    let (y, y_overflow) = self.y.overflowing_div(rhs);
+    let (z, z_overflow) = self.z.overflowing_div(rhs);
+    (Self { x, y, z }, x_overflow || y_overflow || z_overflow)
+  }
+
+  pub fn checked_add(self, rhs: Self) -> Option<Self> {
+    Some(Self {
+      x: self.x.checked_add(rhs.x)?,
+      y: self.y.checked_add(rhs.y)?,
+      z: self.z.checked_add(rhs.z)?,
+    })
+  }
+
+  pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+    Some(Self {
+      x: self.x.checked_sub(rhs.x)?,
+      y: self.y.checked_sub(rhs.y)?,
+      z: self.z.checked_sub(rhs.z)?,
+    })
+  }
+
+  pub fn checked_mul(self, rhs: Q64) -> Option<Self> {
+    Some(Self {
+      x: self.x.checked_mul(rhs)?,
+      y: self.y.checked_mul(rhs)?,
+      z: self.z.checked_mul(rhs)?,
+    })
+  }
+
+  pub fn checked_div(self, rhs: Q64) -> Option<Self> {
+    Some(Self {
+      x: self.x.checked_div(rhs)?,
+      y: self.y.checked_div(rhs)?,
+      z: self.z.checked_div(rhs)?,
+    })
+  }
+
+  pub fn try_div(self, rhs: Q64) -> Result<Self, FixedError> {
+    Ok(Self {
+      x: self.x.try_div(rhs)?,
+      y: self.y.try_div(rhs)?,
+      z: self.z.try_div(rhs)?,
+    })
+  }
+
+  pub fn checked_neg(self) -> Option<Self> {
+    Some(Self {
+      x: self.x.checked_neg()?,
+      y: self.y.checked_neg()?,
+      z: self.z.checked_neg()?,
+    })
+  }
+
+  pub fn wrapping_add(self, rhs: Self) -> Self {
+    self.overflowing_add(rhs).0
+  }
+
+  pub fn wrapping_sub(self, rhs: Self) -> Self {
+    self.overflowing_sub(rhs).0
+  }
+
+  pub fn wrapping_mul(self, rhs: Q64) -> Self {
+    self.overflowing_mul(rhs).0
+  }
+
+  pub fn wrapping_div(self, rhs: Q64) -> Self {
+    self.overflowing_div(rhs).0
+  }
+
+  pub fn saturating_add(self, rhs: Self) -> Self {
+    Self {
+      x: self.x.saturating_add(rhs.x),
+      y: self.y.saturating_add(rhs.y),
+      z: self.z.saturating_add(rhs.z),
+    }
+  }
+
+  pub fn saturating_sub(self, rhs: Self) -> Self {
+    Self {
+      x: self.x.saturating_sub(rhs.x),
+      y: self.y.saturating_sub(rhs.y),
+      z: self.z.saturating_sub(rhs.z),
+    }
+  }
+
+  pub fn saturating_mul(self, rhs: Q64) -> Self {
+    Self {
+      x: self.x.saturating_mul(rhs),
+      y: self.y.saturating_mul(rhs),
+      z: self.z.saturating_mul(rhs),
+    }
+  }
+
+  pub fn saturating_div(self, rhs: Q64) -> Self {
+    Self {
+      x: self.x.saturating_div(rhs),
+      y: self.y.saturating_div(rhs),
+      z: self.z.saturating_div(rhs),
+    }
+  }
+}
+
+/// Prints `(x, y, z)`, passing any precision on to every coordinate.
+impl std::fmt::Display for Q64Vec3 {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match f.precision() {
+      Some(p) => write!(f, "({:.*}, {:.*}, {:.*})", p, self.x, p, self.y, p, self.z),
+      None => write!(f, "({}, {}, {})", self.x, self.y, self.z),
+    }
+  }
+}
+
+impl std::ops::Neg for Q64Vec3 {
+  type Output = Self;
+  fn neg(self) -> Self {
+    Self {
+      x: -self.x,
+      y: -self.y,
+      z: -self.z,
+    }
+  }
+}
+
+impl std::ops::Add for Q64Vec3 {
+  type Output = Self;
+  fn add(self, rhs: Self) -> Self {
+    Self {
+      x: self.x + rhs.x,
+      y: self.y + rhs.y,
+      z: self.z + rhs.z,
+    }
+  }
+}
+
+impl std::ops::Sub for Q64Vec3 {
+  type Output = Self;
+  fn sub(self, rhs: Self) -> Self {
+    Self {
+      x: self.x - rhs.x,
+      y: self.y - rhs.y,
+      z: self.z - rhs.z,
+    }
+  }
+}
+
+impl std::ops::Mul<Q64> for Q64Vec3 {
+  type Output = Self;
+  fn mul(self, rhs: Q64) -> Self {
+    Self {
+      x: self.x * rhs,
+      y: self.y * rhs,
+      z: self.z * rhs,
+    }
+  }
+}
+
+impl std::ops::Mul<Q64Vec3> for Q64 {
+  type Output = Q64Vec3;
+  fn mul(self, rhs: Q64Vec3) -> Q64Vec3 {
+    rhs * self
+  }
+}
+
+impl std::ops::Div<Q64> for Q64Vec3 {
+  type Output = Self;
+  fn div(self, rhs: Q64) -> Self {
+    Self {
+      x: self.x / rhs,
+      y: self.y / rhs,
+      z: self.z / rhs,
+    }
+  }
+}
+
+impl std::iter::Sum for Q64Vec3 {
+  fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+    iter.fold(Self::ZERO, |a, b| a + b)
+  }
+}
+
+impl<'a> std::iter::Sum<&'a Self> for Q64Vec3 {
+  fn sum<I: Iterator<Item = &'a Self>>(iter: I) -> Self {
+    iter.copied().sum()
+  }
+}
+
+impl std::ops::AddAssign for Q64Vec3 {
+  fn add_assign(&mut self, rhs: Self) {
+    *self = *self + rhs;
+  }
+}
+
+impl std::ops::SubAssign for Q64Vec3 {
+  fn sub_assign(&mut self, rhs: Self) {
+    *self = *self - rhs;
+  }
+}
+
+impl std::ops::MulAssign<Q64> for Q64Vec3 {
+  fn mul_assign(&mut self, rhs: Q64) {
+    *self = *self * rhs;
+  }
+}
+
+impl std::ops::DivAssign<Q64> for Q64Vec3 {
+  fn div_assign(&mut self, rhs: Q64) {
+    *self = *self / rhs;
+  }
+}
+
+impl ::serde::Serialize for Q64Vec3 {
+  fn serialize<S: ::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format!("{},{},{}", self.x.0, self.y.0, self.z.0))
+  }
+}
+
+impl<'de> ::serde::Deserialize<'de> for Q64Vec3 {
+  fn deserialize<D: ::serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    Ok(Self::from_bits(parse_raw_parts(&String::deserialize(deserializer)?)?))
+  }
+}
+
+/// A quaternion `w + xi + yj + zk`. The rotation methods expect unit quaternions, which is what the
+/// constructors here produce.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Q64Quat {
+  pub w: Q64,
+  pub x: Q64,
+  pub y: Q64,
+  pub z: Q64,
+}
+
+impl Default for Q64Quat {
+  fn default() -> Self {
+    Self::IDENTITY
+  }
+}
+
+impl Q64Quat {
+  pub const IDENTITY: Self = Self {
+    w: Q64::ONE,
+    x: Q64::ZERO,
+    y: Q64::ZERO,
+    z: Q64::ZERO,
+  };
+
+  pub const fn new(w: Q64, x: Q64, y: Q64, z: Q64) -> Self {
+    Self { w, x, y, z }
+  }
+
+  /// The rotation by `angle` radians counterclockwise about `axis`, which needn't be normalized.
+  /// A zero axis gives the identity.
+  pub fn from_axis_angle(axis: Q64Vec3, angle: Q64) -> Self {
+    let axis = axis.normalized();
+    if axis == Q64Vec3::ZERO {
+      return Self::IDENTITY;
+    }
+    let (sin, cos) = (angle * Q64::HALF).sin_cos();
+    Self::from_parts(cos, axis * sin)
+  }
+
+  fn from_parts(w: Q64, v: Q64Vec3) -> Self {
+    Self::new(w, v.x, v.y, v.z)
+  }
+
+  /// The vector part `(x, y, z)`.
+  pub fn vector(self) -> Q64Vec3 {
+    Q64Vec3::new(self.x, self.y, self.z)
+  }
+
+  fn to_bits(self) -> [i64; 4] {
+    [self.w.to_bits(), self.x.to_bits(), self.y.to_bits(), self.z.to_bits()]
+  }
+
+  fn from_bits([w, x, y, z]: [i64; 4]) -> Self {
+    Self::new(Q64::from_bits(w), Q64::from_bits(x), Q64::from_bits(y), Q64::from_bits(z))
+  }
+
+  /// The inverse rotation, for a unit quaternion.
+  pub fn conjugate(self) -> Self {
+    Self::from_parts(self.w, -self.vector())
+  }
+
+  pub fn dot(self, rhs: Self) -> Q64 {
+    self.w * rhs.w + self.vector().dot(rhs.vector())
+  }
+
+  /// Saturates like `Q64Vec3::norm`.
+  pub fn norm(self) -> Q64 {
+    Q64::from_bits(norm_bits(&self.to_bits()).min(i64::MAX as u128) as i64)
+  }
+
+  /// Rescales to unit length, to undo the drift of repeated composition. Zero stays zero.
+  pub fn normalized(self) -> Self {
+    Self::from_bits(normalized_bits(self.to_bits()))
+  }
+
+  /// Rotates `v` by this quaternion.
+  pub fn rotate(self, v: Q64Vec3) -> Q64Vec3 {
+    // v + 2w (u x v) + 2 u x (u x v), with u the vector part.
+    let u = self.vector();
+    let t = u.cross(v) * Q64::from_i32(2);
+    v + t * self.w + u.cross(t)
+  }
+
+  /// Spherical linear interpolation along the shorter arc, from `self` at `t = 0` to `other` at
+  /// `t = 1`. The result is normalized.
+  pub fn slerp(self, other: Self, t: Q64) -> Self {
+    let (other, cos) = match self.dot(other) {
+      cos if cos < Q64::ZERO => (-other, -cos),
+      cos => (other, cos),
+    };
+    // Below an angle of about 2^-7, dividing by sin(theta) loses more than interpolating linearly.
+    if cos > Q64::ONE - Q64::from_bits(1 << 16) {
+      return self.scaled(Q64::ONE - t).plus(other.scaled(t)).normalized();
+    }
+    let theta = cos.min(Q64::ONE).acos();
+    let sin_theta = theta.sin();
+    let a = ((Q64::ONE - t) * theta).sin() / sin_theta;
+    let b = (t * theta).sin() / sin_theta;
+    self.scaled(a).plus(other.scaled(b)).normalized()
+  }
+
+  fn scaled(self, k: Q64) -> Self {
+    Self::from_parts(self.w * k, self.vector() * k)
+  }
+
+  fn plus(self, rhs: Self) -> Self {
+    Self::from_parts(self.w + rhs.w, self.vector() + rhs.vector())
+  }
+}
+
+impl std::ops::Neg for Q64Quat {
+  type Output = Self;
+  fn neg(self) -> Self {
+    Self::from_parts(-self.w, -self.vector())
+  }
+}
+
+/// The Hamilton product. As rotations, `a * b` applies `b` first and then `a`.
+impl std::ops::Mul for Q64Quat {
+  type Output = Self;
+  fn mul(self, rhs: Self) -> Self {
+    let (u, v) = (self.vector(), rhs.vector());
+    Self::from_parts(self.w * rhs.w - u.dot(v), v * self.w + u * rhs.w + u.cross(v))
+  }
+}
+
+impl std::ops::MulAssign for Q64Quat {
+  fn mul_assign(&mut self, rhs: Self) {
+    *self = *self * rhs;
+  }
+}
+
+impl ::serde::Serialize for Q64Quat {
+  fn serialize<S: ::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format!("{},{},{},{}", self.w.0, self.x.0, self.y.0, self.z.0))
+  }
+}
+
+impl<'de> ::serde::Deserialize<'de> for Q64Quat {
+  fn deserialize<D: ::serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    Ok(Self::from_bits(parse_raw_parts(&String::deserialize(deserializer)?)?))
+  }
+}
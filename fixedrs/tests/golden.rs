@@ -0,0 +1,266 @@
+//! Golden vectors pinning the exact raw outputs of the deterministic math functions.
+//!
+//! Any change that alters even one bit of these outputs fails here. If the change is intentional,
+//! regenerate the files with `FIXEDRS_BLESS=1 cargo test --test golden` and commit the diff.
+//!
+//! The files were first generated by running these same inputs through the original `Q64`
+//! implementation, from before `Fixed` was generic. `sin_fast.txt` and `cos_fast.txt` pin the
+//! original `Q64::sin` and `Q64::cos`, which are now `sin_fast` and `cos_fast`; they are
+//! byte-identical to the output of the original implementation, and must stay so. `sin.txt` and
+//! `cos.txt` pin the accurate versions that replaced them.
+
+use fixedrs::geometry::Polygon;
+use fixedrs::physics::{Body, Collider, World};
+use fixedrs::{Q64, Q64Rng, Q64Vec};
+
+const CASES: usize = 1024;
+
+/// splitmix64, so that the inputs don't depend on any external crate.
+struct Inputs(u64);
+
+impl Inputs {
+  fn next_u64(&mut self) -> u64 {
+    self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+    let mut z = self.0;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+  }
+
+  /// A raw value with a random magnitude of up to `max_bits` bits and a random sign.
+  fn next_raw(&mut self, max_bits: u32) -> i64 {
+    let bits = (self.next_u64() % max_bits as u64) as u32 + 1;
+    let magnitude = (self.next_u64() >> (64 - bits)) as i64;
+    match self.next_u64() & 1 {
+      0 => magnitude,
+      _ => -magnitude,
+    }
+  }
+
+  fn next_vec(&mut self, max_bits: u32) -> Q64Vec {
+    Q64Vec::new(Q64::from_bits(self.next_raw(max_bits)), Q64::from_bits(self.next_raw(max_bits)))
+  }
+}
+
+/// Inputs for the trig functions: a sweep over several periods, special points, and random values.
+fn angle_inputs() -> Vec<Q64> {
+  let mut inputs = vec![Q64::ZERO, Q64::EPSILON, -Q64::EPSILON, Q64::PI_OVER_TWO, Q64::PI, Q64::PI_TIMES_2];
+  for i in -256..256 {
+    inputs.push(Q64::from_bits(i * (Q64::PI_TIMES_2.0 / 64) + i));
+  }
+  let mut rng = Inputs(1);
+  while inputs.len() < CASES {
+    inputs.push(Q64::from_bits(rng.next_raw(44)));
+  }
+  inputs
+}
+
+fn vec_inputs() -> Vec<Q64Vec> {
+  let mut inputs = vec![Q64Vec::ZERO, Q64Vec::from_i32(1, 0), Q64Vec::from_i32(0, -1), Q64Vec::from_i32(-3, 4)];
+  // Points on a circle from the original sin and cos, so that these inputs are the same as before
+  // the accurate trig.
+  for i in 0..64 {
+    let angle = Q64::PI_TIMES_2 * Q64::from_i32(i) / Q64::from_i32(64);
+    inputs.push(Q64Vec::new(angle.cos_fast(), angle.sin_fast()) * Q64::from_i32(7));
+  }
+  let mut rng = Inputs(2);
+  while inputs.len() < CASES {
+    let max_bits = [8, 24, 33, 40, 46][inputs.len() % 5];
+    inputs.push(rng.next_vec(max_bits));
+  }
+  inputs
+}
+
+fn check_golden(name: &str, lines: Vec<String>) {
+  let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(format!("{}.txt", name));
+  let actual = lines.join("\n") + "\n";
+  if std::env::var_os("FIXEDRS_BLESS").is_some() {
+    std::fs::write(&path, actual).unwrap();
+    return;
+  }
+  let expected = std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("reading {}: {}", path.display(), e));
+  for (i, (expected, actual)) in expected.lines().zip(actual.lines()).enumerate() {
+    assert_eq!(expected, actual, "{}.txt line {} changed", name, i + 1);
+  }
+  assert_eq!(expected.lines().count(), actual.lines().count(), "{}.txt has a different length", name);
+}
+
+#[test]
+fn golden_sqrt() {
+  let mut inputs: Vec<Q64> = (0..256).map(Q64::from_bits).collect();
+  inputs.extend([Q64::ONE, Q64::from_i32(2), Q64::from_bits(i64::MAX)]);
+  let mut rng = Inputs(3);
+  while inputs.len() < CASES {
+    inputs.push(Q64::from_bits(rng.next_raw(63).abs()));
+  }
+  check_golden("sqrt", inputs.iter().map(|x| format!("{} {}", x.0, x.sqrt().0)).collect());
+}
+
+#[test]
+fn golden_sin() {
+  check_golden("sin", angle_inputs().iter().map(|x| format!("{} {}", x.0, x.sin().0)).collect());
+}
+
+#[test]
+fn golden_cos() {
+  check_golden("cos", angle_inputs().iter().map(|x| format!("{} {}", x.0, x.cos().0)).collect());
+}
+
+#[test]
+fn golden_sin_fast() {
+  check_golden("sin_fast", angle_inputs().iter().map(|x| format!("{} {}", x.0, x.sin_fast().0)).collect());
+}
+
+#[test]
+fn golden_cos_fast() {
+  check_golden("cos_fast", angle_inputs().iter().map(|x| format!("{} {}", x.0, x.cos_fast().0)).collect());
+}
+
+#[test]
+fn golden_get_angle() {
+  check_golden("get_angle", vec_inputs().iter().map(|v| format!("{} {} {}", v.x.0, v.y.0, v.get_angle().0)).collect());
+}
+
+#[test]
+fn golden_get_angle_fast() {
+  check_golden(
+    "get_angle_fast",
+    vec_inputs().iter().map(|v| format!("{} {} {}", v.x.0, v.y.0, v.get_angle_fast().0)).collect(),
+  );
+}
+
+#[test]
+fn golden_atan2() {
+  check_golden("atan2", vec_inputs().iter().map(|v| format!("{} {} {}", v.y.0, v.x.0, v.y.atan2(v.x).0)).collect());
+}
+
+#[test]
+fn golden_asin_acos() {
+  let mut inputs: Vec<Q64> = (-256..=256).map(|i| Q64::from_bits(i << 24)).collect();
+  inputs.extend([Q64::ONE - Q64::EPSILON, -Q64::ONE + Q64::EPSILON]);
+  let mut rng = Inputs(4);
+  while inputs.len() < CASES {
+    inputs.push(Q64::from_bits(rng.next_raw(33).clamp(-Q64::ONE.0, Q64::ONE.0)));
+  }
+  check_golden(
+    "asin_acos",
+    inputs.iter().map(|x| format!("{} {} {}", x.0, x.asin().0, x.acos().0)).collect(),
+  );
+}
+
+fn show(x: Option<Q64>) -> String {
+  x.map_or("-".to_string(), |x| x.0.to_string())
+}
+
+#[test]
+fn golden_exp() {
+  let mut inputs: Vec<Q64> = (-64..64).map(|i| Q64::from_bits(i << 28)).collect();
+  let mut rng = Inputs(5);
+  while inputs.len() < CASES {
+    inputs.push(Q64::from_bits(rng.next_raw(37)));
+  }
+  check_golden("exp", inputs.iter().map(|x| format!("{} {} {}", x.0, show(x.exp()), show(x.exp2()))).collect());
+}
+
+#[test]
+fn golden_ln() {
+  let mut inputs: Vec<Q64> = (1..128).map(|i| Q64::from_bits(i << 28)).collect();
+  let mut rng = Inputs(6);
+  while inputs.len() < CASES {
+    inputs.push(Q64::from_bits(rng.next_raw(63).abs().max(1)));
+  }
+  check_golden("ln", inputs.iter().map(|x| format!("{} {} {}", x.0, show(x.ln()), show(x.log2()))).collect());
+}
+
+#[test]
+fn golden_powf() {
+  let mut rng = Inputs(7);
+  let inputs: Vec<(Q64, Q64)> =
+    (0..CASES).map(|_| (Q64::from_bits(rng.next_raw(40).abs()), Q64::from_bits(rng.next_raw(36)))).collect();
+  check_golden("powf", inputs.iter().map(|(x, y)| format!("{} {} {}", x.0, y.0, show(x.powf(*y)))).collect());
+}
+
+#[test]
+fn golden_norm() {
+  check_golden("norm", vec_inputs().iter().map(|v| format!("{} {} {}", v.x.0, v.y.0, v.norm().0)).collect());
+}
+
+#[test]
+fn golden_normalized() {
+  check_golden(
+    "normalized",
+    vec_inputs()
+      .iter()
+      .map(|v| {
+        let n = v.normalized();
+        format!("{} {} {} {}", v.x.0, v.y.0, n.x.0, n.y.0)
+      })
+      .collect(),
+  );
+}
+
+#[test]
+fn golden_rng() {
+  let mut rng = Q64Rng::new(0x5eed);
+  check_golden(
+    "rng",
+    (0..CASES)
+      .map(|_| {
+        let (raw, unit) = (rng.next_u64(), rng.next_q64_unit().0);
+        let range = rng.range(Q64::from_i32(-3), Q64::from_f64(0.5)).0;
+        let v = rng.unit_vector();
+        format!("{} {} {} {} {} {}", raw, unit, range, v.x.0, v.y.0, rng.standard_normal().0)
+      })
+      .collect(),
+  );
+}
+
+#[test]
+fn golden_physics() {
+  let mut world = World::new(Q64::ONE / Q64::from_i32(60));
+  world.gravity = Q64Vec::from_i32(0, -10);
+  world.add_body(Body {
+    position: Q64Vec::from_i32(0, -1),
+    friction: Q64::HALF,
+    ..Body::new_static(Collider::Aabb { half_extents: Q64Vec::from_i32(20, 1) })
+  });
+  let half = Q64::HALF;
+  let square = Polygon::new(vec![Q64Vec::new(-half, -half), Q64Vec::new(half, -half), Q64Vec::new(half, half), Q64Vec::new(-half, half)]);
+  let triangle = Polygon::new(vec![Q64Vec::from_i32(-1, -1), Q64Vec::from_i32(1, -1), Q64Vec::from_i32(0, 2)]);
+  for i in 0..3 {
+    world.add_body(Body {
+      position: Q64Vec::new(Q64::ZERO, half + Q64::from_f64(1.05) * Q64::from_i32(i)),
+      friction: Q64::HALF,
+      ..Body::new(Collider::Polygon(square.clone().unwrap()), Q64::ONE)
+    });
+  }
+  world.add_body(Body {
+    position: Q64Vec::from_i32(5, 3),
+    angle: Q64::from_f64(0.4),
+    friction: Q64::HALF,
+    ..Body::new(Collider::Polygon(triangle.unwrap()), Q64::from_i32(2))
+  });
+  let mut rng = Inputs(4);
+  for _ in 0..4 {
+    world.add_body(Body {
+      position: Q64Vec::new(Q64::from_bits(rng.next_raw(35)), Q64::from_i32(6)),
+      velocity: rng.next_vec(34),
+      restitution: half,
+      friction: Q64::from_f64(0.25),
+      ..Body::new(Collider::Circle { radius: half }, Q64::ONE)
+    });
+  }
+  check_golden(
+    "physics",
+    (0..CASES / 2)
+      .map(|_| {
+        world.step();
+        let state = world.bodies().iter().map(|body| {
+          let (p, v) = (body.position, body.velocity);
+          format!("{} {} {} {} {} {}", p.x.0, p.y.0, body.angle.0, v.x.0, v.y.0, body.angular_velocity.0)
+        });
+        state.collect::<Vec<_>>().join(" ")
+      })
+      .collect(),
+  );
+}